@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_slow_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    step(async move {
+      sleep(Duration::from_millis(50)).await;
+
+      send_value(
+        count,
+        detach_shared_session(make_slow_counter_session(count + 1)),
+      )
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  let name2 = name.clone();
+
+  counter.acquire_or(
+    move |chan| {
+      receive_value_from(chan, move |count| {
+        println!("[{}] Received count: {}", name, count);
+
+        release_shared_session(chan, terminate())
+      })
+    },
+    step(async move {
+      println!("[{}] Acquire refused", name2);
+
+      terminate()
+    }),
+  )
+}
+
+async fn burst(
+  policy: OverflowPolicy,
+  capacity: usize,
+)
+{
+  println!("== {:?} with capacity {} ==", policy, capacity);
+
+  let (counter, _) = run_shared_session_with_config(
+    make_slow_counter_session(0),
+    SharedSessionConfig {
+      capacity,
+      overflow: policy,
+      ..SharedSessionConfig::default()
+    },
+  );
+
+  let mut sessions = vec![];
+
+  // The first client holds the lock while the rest arrive, so only
+  // as many of them as the capacity fit in the queue.
+  for i in 0..6 {
+    sessions.push(tokio::spawn(run_session(read_counter_session(
+      format!("{:?}{}", policy, i),
+      counter.clone(),
+    ))));
+
+    sleep(Duration::from_millis(5)).await;
+  }
+
+  for session in sessions {
+    session.await.unwrap().unwrap();
+  }
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  burst(OverflowPolicy::Wait, 2).await;
+  burst(OverflowPolicy::Reject, 2).await;
+  burst(OverflowPolicy::ShedOldest, 2).await;
+  burst(OverflowPolicy::ShedOldest, 0).await;
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+type SharedAdder = LinearToShared<ReceiveValue<u64, SendValue<u64, Release>>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn make_adder_session(total: u64) -> SharedSession<SharedAdder>
+{
+  accept_shared_session_restartable(move || {
+    receive_value(move |n| {
+      send_value(
+        total + n,
+        detach_shared_session(make_adder_session(total + n)),
+      )
+    })
+  })
+}
+
+// Holds on to the lock for a long time, and never gets to release it
+// once the task running it is aborted.
+pub fn holder_session(counter: SharedChannel<SharedCounter>) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[Holder] Holding count {}", count);
+
+      step(async move {
+        for i in 1..=10 {
+          sleep(Duration::from_millis(50)).await;
+
+          println!("[Holder] Still holding after {}ms", i * 50);
+        }
+
+        release_shared_session(chan, terminate())
+      })
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Received count: {}", name, count);
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+// Acquires the adder, and is aborted before it gets to send the value
+// the adder is waiting for.
+pub fn stalled_adder_session(adder: SharedChannel<SharedAdder>)
+  -> Session<End>
+{
+  acquire_shared_session(adder, move |chan| {
+    step(async move {
+      println!("[Stalled] Holding the adder");
+
+      sleep(Duration::from_secs(10)).await;
+
+      send_value_to(
+        chan,
+        100,
+        receive_value_from(chan, move |_| {
+          release_shared_session(chan, terminate())
+        }),
+      )
+    })
+  })
+}
+
+pub fn add_session(
+  name: String,
+  adder: SharedChannel<SharedAdder>,
+  n: u64,
+) -> Session<End>
+{
+  acquire_shared_session(adder, move |chan| {
+    send_value_to(
+      chan,
+      n,
+      receive_value_from(chan, move |total| {
+        println!("[{}] Added {}, total is now {}", name, n, total);
+
+        release_shared_session(chan, terminate())
+      }),
+    )
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let counter = run_shared_session(make_counter_session(0));
+
+  let holder = tokio::spawn(run_session(holder_session(counter.clone())));
+
+  sleep(Duration::from_millis(20)).await;
+
+  let reader = tokio::spawn(run_session(read_counter_session(
+    "Reader".to_string(),
+    counter.clone(),
+  )));
+
+  sleep(Duration::from_millis(100)).await;
+
+  println!("[Main] Aborting holder");
+
+  holder.abort();
+
+  // The holder's lock is released as its tasks are torn down, so the
+  // waiting reader gets the next count and the holder stops printing.
+  reader.await.unwrap().unwrap();
+
+  run_session(read_counter_session("Late".to_string(), counter))
+    .await
+    .unwrap();
+
+  sleep(Duration::from_millis(200)).await;
+
+  let adder = run_shared_session(make_adder_session(0));
+
+  run_session(add_session("First".to_string(), adder.clone(), 5))
+    .await
+    .unwrap();
+
+  let stalled = tokio::spawn(run_session(stalled_adder_session(adder.clone())));
+
+  sleep(Duration::from_millis(20)).await;
+
+  println!("[Main] Aborting stalled adder");
+
+  stalled.abort();
+
+  // The adder is dropped in the middle of its protocol, and goes back
+  // to accepting from the total it was last released with.
+  run_session(add_session("Second".to_string(), adder, 3))
+    .await
+    .unwrap();
+}
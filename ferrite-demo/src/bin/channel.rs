@@ -189,5 +189,5 @@ pub fn channel_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(channel_session()).await;
+  run_session(channel_session()).await.unwrap();
 }
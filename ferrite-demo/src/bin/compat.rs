@@ -0,0 +1,131 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use ipc_channel::ipc;
+use serde::{
+  de::DeserializeOwned,
+  Serialize,
+};
+
+// The counter protocol as the client was compiled with it.
+type CounterV1 =
+  LinearToShared<ExternalChoice<Either<SendValue<u64, Release>, Release>>>;
+
+// A newer provider that offers an extra branch at the end.
+define_choice! { CounterOps;
+  Get : SendValue < u64, Release >,
+  Skip : Release,
+  Add : ReceiveValue < u64, Release >,
+}
+
+type CounterV2 = LinearToShared<ExternalChoice<CounterOps>>;
+
+// A provider that sends its count in an unrelated format.
+type LabelCounter =
+  LinearToShared<ExternalChoice<Either<SendValue<String, Release>, Release>>>;
+
+pub fn counter_v2(count: u64) -> SharedSession<CounterV2>
+{
+  accept_shared_session(move || {
+    offer_choice! {
+      Get => {
+        send_value(count, detach_shared_session(counter_v2(count + 1)))
+      }
+      Skip => {
+        detach_shared_session(counter_v2(count))
+      }
+      Add => {
+        receive_value(move |n| {
+          detach_shared_session(counter_v2(count + n))
+        })
+      }
+    }
+  })
+}
+
+pub fn label_counter() -> SharedSession<LabelCounter>
+{
+  accept_shared_session(move || {
+    offer_choice! {
+      Left => {
+        send_value("one".to_string(), detach_shared_session(label_counter()))
+      }
+      Right => {
+        detach_shared_session(label_counter())
+      }
+    }
+  })
+}
+
+pub fn read_counter(counter: SharedChannel<CounterV1>) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    choose!(
+      chan,
+      Left,
+      receive_value_from(chan, move |count| {
+        println!("[Client] Received count: {}", count);
+
+        release_shared_session(chan, terminate())
+      })
+    )
+  })
+}
+
+// Send a value through an IPC channel, and read it back as a value
+// of another type, as a process compiled against a different version
+// of the protocol would.
+fn transfer<A, B>(value: A) -> Result<B, ipc::IpcError>
+where
+  A: Serialize + DeserializeOwned,
+  B: Serialize + DeserializeOwned,
+{
+  let (sender, receiver) = ipc::channel::<A>().unwrap();
+
+  sender.send(value).unwrap();
+
+  receiver.to_opaque().to::<B>().recv()
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let counter = run_shared_session(counter_v2(0));
+
+  let labels = run_shared_session(label_counter());
+
+  match transfer::<_, SharedChannel<CounterV1>>(counter.clone()) {
+    Ok(_) => println!("[Exact] Connected to the newer provider"),
+    Err(err) => println!("[Exact] Rejected: {:?}", err),
+  }
+
+  let serialized =
+    serialize_shared_channel(counter, &IpcTransport::channel().unwrap().0)
+      .unwrap();
+
+  let serialized: SerializedSharedChannel<CounterV1> =
+    transfer(serialized).unwrap();
+
+  let counter =
+    deserialize_shared_channel_with(serialized, Compatibility::Subtype)
+      .unwrap();
+
+  println!("[Subtype] Connected to the newer provider");
+
+  run_session(read_counter(counter)).await.unwrap();
+
+  let serialized =
+    serialize_shared_channel(labels, &IpcTransport::channel().unwrap().0)
+      .unwrap();
+
+  let serialized: SerializedSharedChannel<CounterV1> =
+    transfer(serialized).unwrap();
+
+  match deserialize_shared_channel_with(serialized, Compatibility::Subtype) {
+    Ok(_) => println!("[Subtype] Connected to the label provider"),
+    Err(err) => println!("[Subtype] Rejected: {}", err),
+  }
+}
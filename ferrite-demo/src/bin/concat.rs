@@ -100,5 +100,5 @@ pub fn concat_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(concat_session()).await;
+  run_session(concat_session()).await.unwrap();
 }
@@ -55,5 +55,5 @@ fn cut_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(cut_session()).await;
+  run_session(cut_session()).await.unwrap();
 }
@@ -0,0 +1,90 @@
+use ferrite_session::prelude::*;
+use ipc_channel::ipc;
+
+type Greeting = SendValue<String, End>;
+
+type GreetingSource = LinearToShared<SendChannel<Greeting, Release>>;
+
+type GreetingSink = LinearToShared<ReceiveChannel<Greeting, Release>>;
+
+pub fn greeting_source_session(count: u64) -> SharedSession<GreetingSource>
+{
+  accept_shared_session(move || {
+    include_session(
+      send_value(format!("Hello #{} from source", count), terminate()),
+      move |greeting| {
+        send_channel_from(
+          greeting,
+          detach_shared_session(greeting_source_session(count + 1)),
+        )
+      },
+    )
+  })
+}
+
+pub fn greeting_sink_session() -> SharedSession<GreetingSink>
+{
+  accept_shared_session(move || {
+    receive_channel(move |greeting| {
+      receive_value_from(greeting, move |message| {
+        println!("[Sink] Received greeting: {}", message);
+
+        wait(greeting, detach_shared_session(greeting_sink_session()))
+      })
+    })
+  })
+}
+
+pub fn relay_session(
+  source: SharedChannel<GreetingSource>,
+  sink: SharedChannel<GreetingSink>,
+) -> Session<End>
+{
+  acquire_shared_session(source, move |source_chan| {
+    receive_channel_from(source_chan, move |greeting| {
+      release_shared_session(
+        source_chan,
+        acquire_shared_session(sink, move |sink_chan| {
+          send_channel_to(
+            sink_chan,
+            greeting,
+            release_shared_session(sink_chan, terminate()),
+          )
+        }),
+      )
+    })
+  })
+}
+
+pub fn delegate_session() -> Session<End>
+{
+  let source = run_shared_session(greeting_source_session(0));
+  let sink = run_shared_session(greeting_sink_session());
+
+  // Both shared channels are serialized through IPC, so the
+  // greeting channel is delegated from the source to the sink
+  // across two OS sockets.
+  let (sender1, receiver1) = ipc::channel().unwrap();
+  sender1.send(source).unwrap();
+  let source = receiver1.recv().unwrap();
+
+  let (sender2, receiver2) = ipc::channel().unwrap();
+  sender2.send(sink).unwrap();
+  let sink = receiver2.recv().unwrap();
+
+  let mut sessions = vec![];
+
+  for _ in 0..5 {
+    sessions.push(relay_session(source.clone(), sink.clone()));
+  }
+
+  wait_sessions(sessions, terminate())
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  run_session(delegate_session()).await.unwrap();
+}
@@ -0,0 +1,63 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+
+define_choice! { Command;
+  Get : SendValue < String, End >,
+  Put : ReceiveValue < (String, Vec<u8>), End >,
+  Quit : End,
+}
+
+struct WrapIntStream;
+
+impl Wrapper for WrapIntStream
+{
+  type Unwrap = IntStream;
+}
+
+impl DescribeWrapper for WrapIntStream
+{
+  const NAME: &'static str = "IntStream";
+}
+
+type IntStream = SendValue<u64, Wrap<WrapIntStream>>;
+
+type Stream = Rec<
+  ExternalChoice<
+    Either<
+      Rec<InternalChoice<Either<SendValue<String, Z>, S<Z>>>>,
+      ReceiveValue<String, End>,
+    >,
+  >,
+>;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+type Delegate = ReceiveChannel<
+  SendValue<String, End>,
+  SendChannel<ReceiveValue<u64, End>, End>,
+>;
+
+fn show<A>(name: &str)
+where
+  A: Describe,
+{
+  println!("{:>14} = {}", name, describe::<A>());
+}
+
+pub fn main()
+{
+  show::<Rec<ExternalChoice<Command>>>("Command");
+  show::<IntStream>("IntStream");
+  show::<Stream>("Stream");
+  show::<SharedCounter>("SharedCounter");
+  show::<Delegate>("Delegate");
+
+  // Descriptions are plain data, so they can also be compared or
+  // logged in structured form.
+  assert_eq!(describe::<Stream>(), describe::<Stream>());
+  assert_ne!(describe::<SharedCounter>(), describe::<IntStream>());
+
+  println!("{:?}", describe::<ReceiveValue<u64, End>>());
+}
@@ -57,5 +57,5 @@ pub fn external_choice_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(external_choice_session()).await
+  run_session(external_choice_session()).await.unwrap()
 }
@@ -29,5 +29,5 @@ pub fn hello_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(hello_session()).await
+  run_session(hello_session()).await.unwrap()
 }
@@ -49,5 +49,5 @@ pub fn internal_choice_session() -> Session<End>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(internal_choice_session()).await
+  run_session(internal_choice_session()).await.unwrap()
 }
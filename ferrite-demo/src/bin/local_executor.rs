@@ -0,0 +1,91 @@
+use std::{
+  thread,
+  time::Duration,
+};
+
+use ferrite_session::prelude::*;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!(
+        "[{}] Received count {} on {:?}",
+        name,
+        count,
+        thread::current().id()
+      );
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+// Never gets a reply, and gives up once the timer fires.
+pub fn impatient_session() -> Session<End>
+{
+  timeout(
+    Duration::from_millis(100),
+    step(async move {
+      futures::future::pending::<()>().await;
+
+      terminate()
+    }),
+    step(async move {
+      println!("[Impatient] Gave up waiting");
+
+      terminate()
+    }),
+  )
+}
+
+fn counter_sessions() -> Session<End>
+{
+  let counter = run_shared_session(make_counter_session(0));
+
+  let mut sessions = vec![impatient_session()];
+
+  for i in 0..3 {
+    sessions.push(read_counter_session(
+      format!("Reader{}", i),
+      counter.clone(),
+    ));
+  }
+
+  wait_sessions(sessions, terminate())
+}
+
+pub fn main()
+{
+  env_logger::init();
+
+  println!("[Main] Running on {:?}", thread::current().id());
+
+  // Every session task runs on this thread, interleaved by the local
+  // executor.
+  LocalExecutor::new()
+    .block_on(async { run_session(counter_sessions()).await })
+    .unwrap();
+
+  // Outside of the local executor, sessions keep running on tokio even
+  // though the `local-executor` feature is enabled.
+  tokio::runtime::Runtime::new()
+    .unwrap()
+    .block_on(async { run_session(counter_sessions()).await })
+    .unwrap();
+}
@@ -0,0 +1,119 @@
+use std::env;
+
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use ipc_channel::ipc;
+
+struct WrapCountdown;
+
+impl Wrapper for WrapCountdown
+{
+  type Unwrap = Countdown;
+}
+
+impl ForwardWrapper for WrapCountdown {}
+
+impl DescribeWrapper for WrapCountdown
+{
+  const NAME: &'static str = "Countdown";
+}
+
+type Countdown =
+  InternalChoice<Either<End, SendValue<u64, Wrap<WrapCountdown>>>>;
+
+type CountdownSource = LinearToShared<SendChannel<Countdown, Release>>;
+
+fn countdown(count: u64) -> Session<Countdown>
+{
+  if count == 0 {
+    offer_case(LeftLabel, terminate())
+  } else {
+    offer_case(
+      RightLabel,
+      send_value(
+        count,
+        wrap_session(step(async move { countdown(count - 1) })),
+      ),
+    )
+  }
+}
+
+fn countdown_source(count: u64) -> SharedSession<CountdownSource>
+{
+  accept_shared_session(move || {
+    include_session(countdown(count), move |stream| {
+      send_channel_from(stream, detach_shared_session(countdown_source(count)))
+    })
+  })
+}
+
+fn sum_countdown(
+  expected: u64,
+  total: u64,
+) -> Session<ReceiveChannel<Countdown, End>>
+{
+  receive_channel(move |stream| {
+    case! { stream ;
+      Left => {
+        println!("[Consumer] Received a total of {}", total);
+
+        assert_eq!(total, expected);
+
+        wait(stream, terminate())
+      }
+      Right => {
+        receive_value_from(stream, move |count| {
+          unwrap_session(
+            stream,
+            include_session(sum_countdown(expected, total + count), |next| {
+              send_channel_to(next, stream, forward(next))
+            }),
+          )
+        })
+      }
+    }
+  })
+}
+
+fn consumer(
+  source: SharedChannel<CountdownSource>,
+  count: u64,
+) -> Session<End>
+{
+  acquire_shared_session(source, move |chan| {
+    receive_channel_from(chan, move |stream| {
+      release_shared_session(
+        chan,
+        include_session(
+          sum_countdown(count * (count + 1) / 2, 0),
+          |consumer| send_channel_to(consumer, stream, forward(consumer)),
+        ),
+      )
+    })
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let count: u64 = env::args()
+    .nth(1)
+    .and_then(|arg| arg.parse().ok())
+    .unwrap_or(20_000);
+
+  let source = run_shared_session(countdown_source(count));
+
+  // Every value of the countdown crosses the IPC channel, with each
+  // step of the recursive protocol forwarded in turn.
+  let (sender, receiver) = ipc::channel().unwrap();
+  sender.send(source).unwrap();
+  let source = receiver.recv().unwrap();
+
+  run_session(consumer(source, count)).await.unwrap();
+
+  println!("[Consumer] Forwarded {} values", count);
+}
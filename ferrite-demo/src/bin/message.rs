@@ -57,6 +57,7 @@ async fn use_counter(
     )
   }))
   .await
+  .unwrap()
 }
 
 #[tokio::main]
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    step(async move {
+      sleep(Duration::from_millis(10)).await;
+
+      send_value(
+        count,
+        detach_shared_session(make_counter_session(count + 1)),
+      )
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Received count: {}", name, count);
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let metrics = InMemoryMetrics::new();
+
+  // The same metrics record both the acquirers' wait times and the
+  // shared session's queue and lock hold times.
+  let (counter, shutdown) = run_shared_session_with_config(
+    make_counter_session(0),
+    SharedSessionConfig {
+      capacity: 4,
+      overflow: OverflowPolicy::Reject,
+      metrics: Some(metrics.clone()),
+      ..SharedSessionConfig::default()
+    },
+  );
+
+  let counter = counter.with_metrics(metrics.clone());
+
+  let mut sessions = vec![];
+
+  for i in 0..8 {
+    let counter = counter.clone();
+
+    sessions.push(tokio::spawn(async move {
+      let res =
+        run_session(read_counter_session(format!("Client{}", i), counter))
+          .await;
+
+      if let Err(err) = res {
+        println!("[Client{}] Failed: {}", i, err);
+      }
+    }));
+  }
+
+  for session in sessions {
+    session.await.unwrap();
+  }
+
+  drop(counter);
+
+  shutdown.shutdown().await.unwrap();
+
+  let snapshot = metrics.snapshot();
+
+  println!("acquires: {}", snapshot.acquires);
+  println!("acquires refused: {}", snapshot.acquires_refused);
+  println!("refused by the shared session: {}", snapshot.refused);
+  println!("max queue depth: {}", snapshot.max_queue_depth);
+  println!("max active sessions: {}", snapshot.max_active);
+  println!("locks held: {}", snapshot.locks);
+  println!("max wait: {:?}", snapshot.max_wait);
+  println!(
+    "mean hold: {:?}",
+    snapshot.total_held / snapshot.locks.max(1) as u32
+  );
+}
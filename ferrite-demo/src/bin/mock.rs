@@ -0,0 +1,94 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+
+type Lookup = ReceiveValue<
+  String,
+  InternalChoice<Either<SendValue<u64, End>, SendValue<String, End>>>,
+>;
+
+type Calculator = ExternalChoice<
+  Either<ReceiveValue<u64, SendValue<u64, End>>, SendValue<String, End>>,
+>;
+
+pub fn lookup_session() -> Session<Lookup>
+{
+  receive_value(|name: String| {
+    if name.is_empty() {
+      offer_case!(
+        Right,
+        send_value("name must not be empty".to_string(), terminate())
+      )
+    } else {
+      offer_case!(Left, send_value(name.len() as u64, terminate()))
+    }
+  })
+}
+
+pub fn calculator_session() -> Session<Calculator>
+{
+  offer_choice! {
+    Left => {
+      receive_value(|n: u64| send_value(n * 2, terminate()))
+    }
+    Right => {
+      send_value("calculator".to_string(), terminate())
+    }
+  }
+}
+
+async fn check_lookup(name: &str) -> Result<(), SessionError>
+{
+  let peer = mock_peer(lookup_session()).send(name.to_string()).await?;
+
+  match EitherChoice::from(peer.offer().await?) {
+    Left(peer) => {
+      let (len, peer) = peer.recv().await?;
+
+      println!("[Lookup] {:?} has length {}", name, len);
+
+      peer.wait_end().await
+    }
+    Right(peer) => {
+      let (err, peer) = peer.recv().await?;
+
+      println!("[Lookup] {:?} was refused: {}", name, err);
+
+      peer.wait_end().await
+    }
+  }
+}
+
+async fn check_calculator() -> Result<(), SessionError>
+{
+  let peer = mock_peer(calculator_session()).choose(LeftLabel).await?;
+
+  let (doubled, peer) = peer.send(21).await?.recv().await?;
+
+  println!("[Calculator] doubled 21 to {}", doubled);
+
+  peer.wait_end().await?;
+
+  let (name, peer) = mock_peer(calculator_session())
+    .choose(RightLabel)
+    .await?
+    .recv()
+    .await?;
+
+  println!("[Calculator] named itself {}", name);
+
+  peer.wait_end().await
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  check_lookup("Alice").await.unwrap();
+
+  check_lookup("").await.unwrap();
+
+  check_calculator().await.unwrap();
+}
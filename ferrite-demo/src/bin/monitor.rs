@@ -0,0 +1,142 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use ipc_channel::ipc;
+use serde::{
+  de::DeserializeOwned,
+  Deserialize,
+  Serialize,
+};
+
+type Counter =
+  LinearToShared<ExternalChoice<Either<SendValue<u64, Release>, Release>>>;
+
+// A provider that sends its count without waiting for a choice.
+type Ticker = LinearToShared<SendValue<u64, Release>>;
+
+// A provider that sends its count in a narrower type.
+type ByteCounter =
+  LinearToShared<ExternalChoice<Either<SendValue<u8, Release>, Release>>>;
+
+// The wire layout of a serialized shared channel, as seen by a remote
+// that sends whatever protocol description it likes.
+#[derive(Serialize, Deserialize)]
+struct ForgedSharedChannel
+{
+  acquire: IpcTransport,
+  protocol: ProtocolDesc,
+  phantom: (),
+}
+
+pub fn counter(count: u64) -> SharedSession<Counter>
+{
+  accept_shared_session(move || {
+    offer_choice! {
+      Left => {
+        send_value(count, detach_shared_session(counter(count + 1)))
+      }
+      Right => {
+        detach_shared_session(counter(count))
+      }
+    }
+  })
+}
+
+pub fn ticker(count: u64) -> SharedSession<Ticker>
+{
+  accept_shared_session(move || {
+    send_value(count, detach_shared_session(ticker(count + 1)))
+  })
+}
+
+pub fn byte_counter(count: u8) -> SharedSession<ByteCounter>
+{
+  accept_shared_session(move || {
+    offer_choice! {
+      Left => {
+        send_value(count, detach_shared_session(byte_counter(count + 1)))
+      }
+      Right => {
+        detach_shared_session(byte_counter(count))
+      }
+    }
+  })
+}
+
+pub fn read_counter(counter: SharedChannel<Counter>) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    choose!(
+      chan,
+      Left,
+      receive_value_from(chan, move |count| {
+        println!("[Client] Received count: {}", count);
+
+        release_shared_session(chan, terminate())
+      })
+    )
+  })
+}
+
+fn transfer<A, B>(value: A) -> B
+where
+  A: Serialize + DeserializeOwned,
+  B: Serialize + DeserializeOwned,
+{
+  let (sender, receiver) = ipc::channel::<A>().unwrap();
+
+  sender.send(value).unwrap();
+
+  receiver.to_opaque().to::<B>().recv().unwrap()
+}
+
+// Serialize a shared channel with the description of the counter
+// protocol in place of its own, and receive it back with a monitor on
+// every acquired session.
+fn forge<S>(channel: SharedChannel<S>) -> SharedChannel<Counter>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+{
+  let serialized =
+    serialize_shared_channel(channel, &IpcTransport::channel().unwrap().0)
+      .unwrap();
+
+  let mut forged: ForgedSharedChannel = transfer(serialized);
+
+  forged.protocol = describe::<Counter>();
+
+  let serialized: SerializedSharedChannel<Counter> = transfer(forged);
+
+  deserialize_shared_channel(serialized.monitored()).unwrap()
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let honest = forge(run_shared_session(counter(0)));
+
+  run_session(read_counter(honest.clone())).await.unwrap();
+
+  run_session(read_counter(honest)).await.unwrap();
+
+  // The other providers claim to offer the counter protocol, so the
+  // compatibility check passes and only the monitor can tell that the
+  // messages they send do not follow it.
+  let ticker = forge(run_shared_session(ticker(0)));
+
+  let res = run_session(read_counter(ticker)).await;
+
+  println!("[Client] Ticker: {:?}", res.map_err(|err| err.to_string()));
+
+  let bytes = forge(run_shared_session(byte_counter(0)));
+
+  let res = run_session(read_counter(bytes)).await;
+
+  println!(
+    "[Client] Byte counter: {:?}",
+    res.map_err(|err| err.to_string())
+  );
+}
@@ -85,5 +85,7 @@ fn consumer() -> Session<ReceiveChannel<Stream, End>>
 #[tokio::main]
 pub async fn main()
 {
-  run_session(apply_channel(consumer(), producer())).await
+  run_session(apply_channel(consumer(), producer()))
+    .await
+    .unwrap()
 }
@@ -146,5 +146,5 @@ pub fn pair_session() -> Session<End>
 
 pub async fn main()
 {
-  run_session(pair_session()).await
+  run_session(pair_session()).await.unwrap()
 }
@@ -0,0 +1,80 @@
+use std::time::{
+  Duration,
+  Instant,
+};
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedEcho =
+  LinearToShared<ReceiveValue<String, SendValue<String, Release>>>;
+
+pub fn make_echo_session(replica: usize) -> SharedSession<SharedEcho>
+{
+  accept_shared_session(move || {
+    receive_value(move |msg: String| {
+      step(async move {
+        sleep(Duration::from_millis(100)).await;
+
+        send_value(
+          format!("replica {} echoes {}", replica, msg),
+          detach_shared_session(make_echo_session(replica)),
+        )
+      })
+    })
+  })
+}
+
+pub fn echo_client_session(
+  name: String,
+  echo: SharedChannel<SharedEcho>,
+) -> Session<End>
+{
+  acquire_shared_session(echo, move |chan| {
+    send_value_to(
+      chan,
+      name,
+      receive_value_from(chan, move |reply| {
+        println!("{}", reply);
+
+        release_shared_session(chan, terminate())
+      }),
+    )
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let (echo, shutdown) = run_shared_session_pool_with_config(
+    (make_echo_session(0), (1..3).map(make_echo_session).collect()),
+    SharedSessionConfig::default(),
+  );
+
+  let start = Instant::now();
+
+  let mut sessions = vec![];
+
+  for i in 0..6 {
+    sessions.push(tokio::spawn(run_session(echo_client_session(
+      format!("client {}", i),
+      echo.clone(),
+    ))));
+  }
+
+  for session in sessions {
+    session.await.unwrap().unwrap();
+  }
+
+  // Six clients on three replicas take about two rounds rather than six.
+  println!(
+    "served 6 clients in {}ms",
+    start.elapsed().as_millis() / 100 * 100
+  );
+
+  drop(echo);
+
+  println!("shutdown: {:?}", shutdown.shutdown().await);
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    step(async move {
+      sleep(Duration::from_millis(20)).await;
+
+      send_value(
+        count,
+        detach_shared_session(make_counter_session(count + 1)),
+      )
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Received count: {}", name, count);
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+async fn contend(scheduling: AcquireScheduling)
+{
+  println!("== {:?} ==", scheduling);
+
+  let (counter, shutdown) = run_shared_session_with_config(
+    make_counter_session(0),
+    SharedSessionConfig {
+      scheduling,
+      ..SharedSessionConfig::default()
+    },
+  );
+
+  let batch = counter.clone().with_client(1).with_priority(-1);
+
+  let interactive = counter.clone().with_client(2);
+
+  let mut sessions = vec![];
+
+  // The batch job queues up its acquires before the interactive
+  // client arrives, while the first one is holding the lock.
+  for i in 0..4 {
+    sessions.push(tokio::spawn(run_session(read_counter_session(
+      format!("Batch{}", i),
+      batch.clone(),
+    ))));
+
+    sleep(Duration::from_millis(2)).await;
+  }
+
+  for i in 0..2 {
+    sessions.push(tokio::spawn(run_session(read_counter_session(
+      format!("Interactive{}", i),
+      interactive.clone(),
+    ))));
+
+    sleep(Duration::from_millis(2)).await;
+  }
+
+  for session in sessions {
+    session.await.unwrap().unwrap();
+  }
+
+  drop((counter, batch, interactive));
+
+  shutdown.shutdown().await.unwrap();
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  contend(AcquireScheduling::Fifo).await;
+  contend(AcquireScheduling::Priority).await;
+
+  // Fair scheduling alternates between the two clients once they have
+  // the same priority.
+  println!("== Fair ==");
+
+  let (counter, shutdown) = run_shared_session_with_config(
+    make_counter_session(0),
+    SharedSessionConfig {
+      scheduling: AcquireScheduling::Fair,
+      ..SharedSessionConfig::default()
+    },
+  );
+
+  let mut sessions = vec![];
+
+  for (client, count) in [(1, 4), (2, 2)].iter() {
+    for i in 0..*count {
+      sessions.push(tokio::spawn(run_session(read_counter_session(
+        format!("Client{}-{}", client, i),
+        counter.clone().with_client(*client),
+      ))));
+
+      sleep(Duration::from_millis(2)).await;
+    }
+  }
+
+  for session in sessions {
+    session.await.unwrap().unwrap();
+  }
+
+  drop(counter);
+
+  shutdown.shutdown().await.unwrap();
+}
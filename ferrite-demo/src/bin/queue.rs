@@ -97,5 +97,5 @@ pub fn queue_session() -> Session<End>
 
 pub async fn main()
 {
-  run_session(queue_session()).await;
+  run_session(queue_session()).await.unwrap();
 }
@@ -0,0 +1,196 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use ipc_channel::ipc;
+
+define_choice! { StreamOption;
+  Next: SendValue < u64, Wrap < WrapIntStream > >,
+  Close: End,
+}
+
+pub struct WrapIntStream;
+
+impl Wrapper for WrapIntStream
+{
+  type Unwrap = IntStream;
+}
+
+impl ForwardWrapper for WrapIntStream {}
+
+impl DescribeWrapper for WrapIntStream
+{
+  const NAME: &'static str = "IntStream";
+}
+
+type IntStream = ExternalChoice<StreamOption>;
+
+struct WrapCountdown;
+
+impl Wrapper for WrapCountdown
+{
+  type Unwrap = Countdown;
+}
+
+impl ForwardWrapper for WrapCountdown {}
+
+impl DescribeWrapper for WrapCountdown
+{
+  const NAME: &'static str = "Countdown";
+}
+
+type Countdown =
+  InternalChoice<Either<SendValue<u64, Wrap<WrapCountdown>>, End>>;
+
+type IntSource = LinearToShared<SendChannel<IntStream, Release>>;
+
+type CountdownSource = LinearToShared<SendChannel<Countdown, Release>>;
+
+fn int_stream(count: u64) -> Session<IntStream>
+{
+  offer_choice! {
+    Next => {
+      println!("[IntStream] Producing value: {}", count);
+      send_value(count, wrap_session(int_stream(count + 1)))
+    }
+    Close => {
+      terminate()
+    }
+  }
+}
+
+fn countdown(count: u64) -> Session<Countdown>
+{
+  if count == 0 {
+    offer_case!(Right, terminate())
+  } else {
+    offer_case!(Left, send_value(count, wrap_session(countdown(count - 1))))
+  }
+}
+
+fn int_source() -> SharedSession<IntSource>
+{
+  accept_shared_session(move || {
+    include_session(int_stream(0), move |stream| {
+      send_channel_from(stream, detach_shared_session(int_source()))
+    })
+  })
+}
+
+fn countdown_source(count: u64) -> SharedSession<CountdownSource>
+{
+  accept_shared_session(move || {
+    include_session(countdown(count), move |stream| {
+      send_channel_from(
+        stream,
+        detach_shared_session(countdown_source(count + 1)),
+      )
+    })
+  })
+}
+
+fn take_ints(remaining: u64) -> Session<ReceiveChannel<IntStream, End>>
+{
+  receive_channel(move |stream| {
+    if remaining == 0 {
+      choose!(stream, Close, wait(stream, terminate()))
+    } else {
+      choose!(
+        stream,
+        Next,
+        receive_value_from(stream, move |count| {
+          println!("[IntConsumer] Received value: {}", count);
+
+          unwrap_session(
+            stream,
+            include_session(take_ints(remaining - 1), |next| {
+              send_channel_to(next, stream, forward(next))
+            }),
+          )
+        })
+      )
+    }
+  })
+}
+
+fn drain_countdown() -> Session<ReceiveChannel<Countdown, End>>
+{
+  receive_channel(|stream| {
+    case! { stream ;
+      Left => {
+        receive_value_from(stream, move |count| {
+          println!("[CountdownConsumer] Received value: {}", count);
+
+          unwrap_session(
+            stream,
+            include_session(drain_countdown(), |next| {
+              send_channel_to(next, stream, forward(next))
+            }),
+          )
+        })
+      }
+      Right => {
+        println!("[CountdownConsumer] Countdown finished");
+        wait(stream, terminate())
+      }
+    }
+  })
+}
+
+fn int_consumer(source: SharedChannel<IntSource>) -> Session<End>
+{
+  acquire_shared_session(source, move |chan| {
+    receive_channel_from(chan, move |stream| {
+      release_shared_session(
+        chan,
+        include_session(take_ints(3), |consumer| {
+          send_channel_to(consumer, stream, forward(consumer))
+        }),
+      )
+    })
+  })
+}
+
+fn countdown_consumer(source: SharedChannel<CountdownSource>) -> Session<End>
+{
+  acquire_shared_session(source, move |chan| {
+    receive_channel_from(chan, move |stream| {
+      release_shared_session(
+        chan,
+        include_session(drain_countdown(), |consumer| {
+          send_channel_to(consumer, stream, forward(consumer))
+        }),
+      )
+    })
+  })
+}
+
+pub fn remote_stream_session() -> Session<End>
+{
+  let ints = run_shared_session(int_source());
+  let countdowns = run_shared_session(countdown_source(3));
+
+  // Sending the shared channels through IPC channels causes the
+  // streams they hand out to be forwarded through OS sockets,
+  // just like a stream producer living in a worker process.
+  let (sender1, receiver1) = ipc::channel().unwrap();
+  sender1.send(ints).unwrap();
+  let ints = receiver1.recv().unwrap();
+
+  let (sender2, receiver2) = ipc::channel().unwrap();
+  sender2.send(countdowns).unwrap();
+  let countdowns = receiver2.recv().unwrap();
+
+  wait_sessions(
+    vec![int_consumer(ints), countdown_consumer(countdowns)],
+    terminate(),
+  )
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  run_session(remote_stream_session()).await.unwrap();
+}
@@ -179,5 +179,5 @@ pub fn restaurant_session() -> Session<End>
 
 pub async fn main()
 {
-  run_session(restaurant_session()).await;
+  run_session(restaurant_session()).await.unwrap();
 }
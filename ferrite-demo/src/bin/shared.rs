@@ -96,5 +96,5 @@ pub async fn main()
 {
   env_logger::init();
 
-  run_session(shared_counter_session()).await;
+  run_session(shared_counter_session()).await.unwrap();
 }
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn hold_counter_session(
+  name: &'static str,
+  duration: Duration,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Holding count {}", name, count);
+
+      step(async move {
+        sleep(duration).await;
+
+        println!("[{}] Releasing count {}", name, count);
+
+        release_shared_session(chan, terminate())
+      })
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: &'static str,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  counter.acquire_or(
+    move |chan| {
+      receive_value_from(chan, move |count| {
+        println!("[{}] Received count: {}", name, count);
+
+        release_shared_session(chan, terminate())
+      })
+    },
+    step(async move {
+      println!("[{}] Acquire refused", name);
+
+      terminate()
+    }),
+  )
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let (counter, shutdown) = run_shared_session_with_config(
+    make_counter_session(0),
+    SharedSessionConfig::default(),
+  );
+
+  let holder = tokio::spawn(run_session(hold_counter_session(
+    "Holder",
+    Duration::from_millis(200),
+    counter.clone(),
+  )));
+
+  sleep(Duration::from_millis(50)).await;
+
+  let waiter =
+    tokio::spawn(run_session(read_counter_session("Waiter", counter.clone())));
+
+  sleep(Duration::from_millis(50)).await;
+
+  // The waiting client is refused right away, while the holder is
+  // allowed to finish before the shutdown resolves. Clients arriving
+  // in the meantime are refused as well.
+  let stopping = tokio::spawn(shutdown.shutdown());
+
+  sleep(Duration::from_millis(20)).await;
+
+  run_session(read_counter_session("During", counter.clone()))
+    .await
+    .unwrap();
+
+  stopping.await.unwrap().unwrap();
+  println!("[Main] Shared counter has shut down");
+
+  holder.await.unwrap().unwrap();
+  waiter.await.unwrap().unwrap();
+
+  let res = run_session(read_counter_session("Late", counter)).await;
+  println!("[Late] Acquire after shutdown: {:?}", res);
+
+  let (counter, shutdown) = run_shared_session_with_config(
+    make_counter_session(0),
+    SharedSessionConfig::default(),
+  );
+
+  tokio::spawn(run_session(hold_counter_session(
+    "Stuck",
+    Duration::from_secs(60),
+    counter,
+  )));
+
+  sleep(Duration::from_millis(50)).await;
+
+  let res = shutdown.shutdown_timeout(Duration::from_millis(100)).await;
+  println!("[Main] Shutdown with stuck holder: {:?}", res);
+}
@@ -0,0 +1,102 @@
+use std::{
+  panic,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+
+use ferrite_session::prelude::*;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+  log: Log,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      log.lock().unwrap().push(format!("{}:{}", name, count));
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+// The order in which four readers get to the counter, which depends
+// only on how their tasks are scheduled.
+async fn interleaving() -> Vec<String>
+{
+  let log: Log = Arc::new(Mutex::new(Vec::new()));
+
+  let counter = run_shared_session(make_counter_session(0));
+
+  let sessions = (0..4)
+    .map(|i| {
+      read_counter_session(format!("R{}", i), counter.clone(), log.clone())
+    })
+    .collect();
+
+  run_session(wait_sessions(sessions, terminate()))
+    .await
+    .unwrap();
+
+  let log = log.lock().unwrap().clone();
+
+  log
+}
+
+pub fn main()
+{
+  env_logger::init();
+
+  let first = Simulation::new(42).run(interleaving());
+  let second = Simulation::new(42).run(interleaving());
+
+  println!("[Seed 42] {}", first.join(" "));
+  println!("[Seed 42] {}", second.join(" "));
+
+  assert_eq!(first, second);
+
+  for seed in 0..4 {
+    let order = Simulation::new(seed).run(interleaving());
+
+    println!("[Seed {}] {}", seed, order.join(" "));
+  }
+
+  // Look for a seed under which the first reader does not get the
+  // first count, and replay it.
+  let hook = panic::take_hook();
+  panic::set_hook(Box::new(|_| {}));
+
+  let res = explore_seeds(0..100, || async {
+    let order = interleaving().await;
+
+    assert_eq!(order[0], "R0:0");
+  });
+
+  panic::set_hook(hook);
+
+  match res {
+    Ok(()) => println!("[Explore] R0 always goes first"),
+    Err(seed) => {
+      let order = Simulation::new(seed).run(interleaving());
+
+      println!("[Explore] Seed {} replays as {}", seed, order.join(" "));
+    }
+  }
+}
@@ -0,0 +1,198 @@
+use std::{
+  env,
+  time::Duration,
+};
+
+use ferrite_session::prelude::*;
+use tokio::{
+  net::{
+    TcpListener,
+    TcpStream,
+    UnixListener,
+    UnixStream,
+  },
+  time::sleep,
+};
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+type GreetingSource =
+  LinearToShared<SendChannel<SendValue<String, End>, Release>>;
+
+type SharedLabel = LinearToShared<SendValue<String, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn make_greeting_session(count: u64) -> SharedSession<GreetingSource>
+{
+  accept_shared_session(move || {
+    include_session(
+      send_value(format!("Hello #{} over unix socket", count), terminate()),
+      move |greeting| {
+        send_channel_from(
+          greeting,
+          detach_shared_session(make_greeting_session(count + 1)),
+        )
+      },
+    )
+  })
+}
+
+pub fn read_counter_session(
+  name: String,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Received count: {}", name, count);
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+pub fn hold_counter_session(
+  counter: SharedChannel<SharedCounter>
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[Holder] Holding count: {}", count);
+
+      step(async move {
+        sleep(Duration::from_millis(200)).await;
+
+        release_shared_session(chan, terminate())
+      })
+    })
+  })
+}
+
+// Gives up while the counter is held, so the remote grant arrives
+// only after it has gone.
+pub fn impatient_counter_session(
+  counter: SharedChannel<SharedCounter>
+) -> Session<End>
+{
+  acquire_shared_session_with_timeout(
+    counter,
+    Duration::from_millis(50),
+    move |chan| {
+      receive_value_from(chan, move |count| {
+        println!("[Impatient] Received count: {}", count);
+
+        release_shared_session(chan, terminate())
+      })
+    },
+    step(async move {
+      println!("[Impatient] Timed out");
+
+      terminate()
+    }),
+  )
+}
+
+pub fn read_greeting_session(
+  source: SharedChannel<GreetingSource>
+) -> Session<End>
+{
+  acquire_shared_session(source, move |chan| {
+    receive_channel_from(chan, move |greeting| {
+      receive_value_from(greeting, move |message| {
+        println!("[Greeting] Received: {}", message);
+
+        wait(greeting, release_shared_session(chan, terminate()))
+      })
+    })
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let address = tcp_listener.local_addr().unwrap();
+
+  let counter = run_shared_session(make_counter_session(0));
+  tokio::spawn(listen_shared_channel(counter, tcp_listener, Json));
+
+  let socket_path = env::temp_dir()
+    .join(format!("ferrite-socket-demo-{}.sock", std::process::id()));
+  let unix_listener = UnixListener::bind(&socket_path).unwrap();
+
+  let greeting = run_shared_session(make_greeting_session(0));
+  tokio::spawn(listen_shared_channel(greeting, unix_listener, Cbor));
+
+  // Each connection gives a local proxy of the remote shared channel,
+  // with every acquire multiplexed over the same socket. Both ends
+  // of a connection must agree on the wire format.
+  let counter1: SharedChannel<SharedCounter> =
+    connect_shared_channel(TcpStream::connect(address).await.unwrap(), Json)
+      .await
+      .unwrap();
+  let counter2: SharedChannel<SharedCounter> = connect_shared_channel_with(
+    TcpStream::connect(address).await.unwrap(),
+    Json,
+    Compatibility::Subtype,
+  )
+  .await
+  .unwrap();
+  let greeting: SharedChannel<GreetingSource> = connect_shared_channel(
+    UnixStream::connect(&socket_path).await.unwrap(),
+    Cbor,
+  )
+  .await
+  .unwrap();
+
+  // The listener announces the protocol it serves when a connection is
+  // made, so a peer expecting another protocol is turned away before
+  // it gets to acquire anything.
+  let res: Result<SharedChannel<SharedLabel>, _> =
+    connect_shared_channel(TcpStream::connect(address).await.unwrap(), Json)
+      .await;
+
+  if let Err(err) = res {
+    println!("[Label] Rejected: {}", err);
+  }
+
+  let mut sessions = vec![];
+
+  for i in 0..5 {
+    sessions.push(read_counter_session(format!("A{}", i), counter1.clone()));
+    sessions.push(read_counter_session(format!("B{}", i), counter2.clone()));
+    sessions.push(read_greeting_session(greeting.clone()));
+  }
+
+  run_session(wait_sessions(sessions, terminate()))
+    .await
+    .unwrap();
+
+  let holder = tokio::spawn(run_session(hold_counter_session(counter1)));
+
+  sleep(Duration::from_millis(20)).await;
+
+  run_session(impatient_counter_session(counter2.clone()))
+    .await
+    .unwrap();
+
+  holder.await.unwrap().unwrap();
+
+  // The lock granted to the impatient acquirer is left unclaimed, and
+  // goes to the next acquirer over the same connection.
+  run_session(read_counter_session("Again".to_string(), counter2))
+    .await
+    .unwrap();
+
+  std::fs::remove_file(&socket_path).unwrap();
+}
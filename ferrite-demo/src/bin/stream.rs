@@ -42,5 +42,5 @@ pub fn stream_session() -> Session<End>
 
 pub async fn main()
 {
-  run_session(stream_session()).await;
+  run_session(stream_session()).await.unwrap();
 }
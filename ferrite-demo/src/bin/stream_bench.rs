@@ -0,0 +1,299 @@
+use std::{
+  env,
+  future::Future,
+  sync::atomic::{
+    AtomicU64,
+    Ordering,
+  },
+  time::{
+    Duration,
+    Instant,
+  },
+};
+
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use ipc_channel::ipc;
+
+type Countdown = Rec<InternalChoice<Either<End, SendValue<u64, Z>>>>;
+
+struct WrapCountdown;
+
+impl Wrapper for WrapCountdown
+{
+  type Unwrap = WrappedCountdown;
+}
+
+impl ForwardWrapper for WrapCountdown {}
+
+impl DescribeWrapper for WrapCountdown
+{
+  const NAME: &'static str = "Countdown";
+}
+
+// The same stream, with its recursion expressed through a wrapper so
+// that it can be forwarded to another process.
+type WrappedCountdown =
+  InternalChoice<Either<End, SendValue<u64, Wrap<WrapCountdown>>>>;
+
+type CountdownSource =
+  LinearToShared<SendChannel<WrappedCountdown, ReceiveValue<u64, Release>>>;
+
+static SPAWNED_TASKS: AtomicU64 = AtomicU64::new(0);
+
+// With spawn_per_step set, each step of the stream is run in a task
+// of its own, the way session steps ran before they were run inline.
+// This is the baseline the inline steps are measured against.
+async fn run_step<T>(
+  spawn_per_step: bool,
+  step: impl Future<Output = T> + Send + 'static,
+) -> T
+where
+  T: Send + 'static,
+{
+  if spawn_per_step {
+    SPAWNED_TASKS.fetch_add(1, Ordering::Relaxed);
+
+    tokio::spawn(step).await.unwrap()
+  } else {
+    step.await
+  }
+}
+
+fn countdown(
+  count: u64,
+  spawn_per_step: bool,
+) -> Session<Countdown>
+{
+  if count == 0 {
+    fix_session(offer_case(LeftLabel, terminate()))
+  } else {
+    fix_session(offer_case(
+      RightLabel,
+      send_value(
+        count,
+        step(run_step(spawn_per_step, async move {
+          countdown(count - 1, spawn_per_step)
+        })),
+      ),
+    ))
+  }
+}
+
+fn sum_countdown(
+  total: u64
+) -> PartialSession<(Countdown, ()), SendValue<u64, End>>
+{
+  unfix_session(
+    Z,
+    case! { Z ;
+      Left => {
+        wait(Z, send_value(total, terminate()))
+      }
+      Right => {
+        receive_value_from(Z, move |count| {
+          sum_countdown(total + count)
+        })
+      }
+    },
+  )
+}
+
+fn wrapped_countdown(
+  count: u64,
+  spawn_per_step: bool,
+) -> Session<WrappedCountdown>
+{
+  if count == 0 {
+    offer_case(LeftLabel, terminate())
+  } else {
+    offer_case(
+      RightLabel,
+      send_value(
+        count,
+        wrap_session(step(run_step(spawn_per_step, async move {
+          wrapped_countdown(count - 1, spawn_per_step)
+        }))),
+      ),
+    )
+  }
+}
+
+fn sum_wrapped_countdown(
+  total: u64
+) -> PartialSession<(WrappedCountdown, ()), SendValue<u64, End>>
+{
+  case! { Z ;
+    Left => {
+      wait(Z, send_value(total, terminate()))
+    }
+    Right => {
+      receive_value_from(Z, move |count| {
+        unwrap_session(Z, sum_wrapped_countdown(total + count))
+      })
+    }
+  }
+}
+
+// Sends the countdown to its acquirer, and gets the sum back before
+// the lock is released.
+fn countdown_source(
+  count: u64,
+  spawn_per_step: bool,
+) -> SharedSession<CountdownSource>
+{
+  accept_shared_session(move || {
+    include_session(wrapped_countdown(count, spawn_per_step), move |stream| {
+      send_channel_from(
+        stream,
+        receive_value(move |total| {
+          assert_eq!(total, count * (count + 1) / 2);
+
+          detach_shared_session(countdown_source(count, spawn_per_step))
+        }),
+      )
+    })
+  })
+}
+
+fn remote_sum(source: SharedChannel<CountdownSource>) -> Session<End>
+{
+  acquire_shared_session(source, move |chan| {
+    receive_channel_from(chan, move |stream| {
+      include_session(
+        receive_channel(|_| sum_wrapped_countdown(0)),
+        move |sum| {
+          send_channel_to(
+            sum,
+            stream,
+            receive_value_from(sum, move |total| {
+              wait(
+                sum,
+                send_value_to(
+                  chan,
+                  total,
+                  release_shared_session(chan, terminate()),
+                ),
+              )
+            }),
+          )
+        },
+      )
+    })
+  })
+}
+
+struct Measurement
+{
+  elapsed: Duration,
+  tasks: u64,
+}
+
+async fn measure<Fut>(run: impl FnOnce() -> Fut) -> Measurement
+where
+  Fut: Future<Output = ()>,
+{
+  let tasks = SPAWNED_TASKS.load(Ordering::Relaxed);
+
+  let start = Instant::now();
+
+  run().await;
+
+  Measurement {
+    elapsed: start.elapsed(),
+    tasks: SPAWNED_TASKS.load(Ordering::Relaxed) - tasks,
+  }
+}
+
+// Each stream is run with its steps run inline, and again with a task
+// spawned for every step as the baseline.
+fn report(
+  label: &str,
+  steps: u64,
+  inline: Measurement,
+  spawned: Measurement,
+)
+{
+  println!("{}: streamed {} values", label, steps);
+
+  for (mode, measurement) in
+    [("inline steps", inline), ("spawn per step", spawned)]
+  {
+    println!(
+      "  {:<16} {:>12.3?} {:>12.0} values/s {:>10} tasks spawned",
+      mode,
+      measurement.elapsed,
+      steps as f64 / measurement.elapsed.as_secs_f64(),
+      measurement.tasks
+    );
+  }
+}
+
+async fn local_sum(
+  steps: u64,
+  spawn_per_step: bool,
+)
+{
+  let total = run_session_with_result(include_session(
+    countdown(steps, spawn_per_step),
+    |_| sum_countdown(0),
+  ))
+  .await
+  .unwrap();
+
+  assert_eq!(total, steps * (steps + 1) / 2);
+}
+
+// Every value crosses the IPC channel, with each step of the stream
+// forwarded in turn.
+fn remote_source(
+  steps: u64,
+  spawn_per_step: bool,
+) -> SharedChannel<CountdownSource>
+{
+  let source = run_shared_session(countdown_source(steps, spawn_per_step));
+
+  let (sender, receiver) = ipc::channel().unwrap();
+  sender.send(source).unwrap();
+  receiver.recv().unwrap()
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let steps: u64 = env::args()
+    .nth(1)
+    .and_then(|arg| arg.parse().ok())
+    .unwrap_or(1_000_000);
+
+  let remote_steps: u64 = env::args()
+    .nth(2)
+    .and_then(|arg| arg.parse().ok())
+    .unwrap_or(10_000);
+
+  let inline = measure(|| local_sum(steps, false)).await;
+
+  let spawned = measure(|| local_sum(steps, true)).await;
+
+  report("in process", steps, inline, spawned);
+
+  let source = remote_source(remote_steps, false);
+
+  let inline = measure(|| async {
+    run_session(remote_sum(source)).await.unwrap();
+  })
+  .await;
+
+  let source = remote_source(remote_steps, true);
+
+  let spawned = measure(|| async {
+    run_session(remote_sum(source)).await.unwrap();
+  })
+  .await;
+
+  report("over ipc", remote_steps, inline, spawned);
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_slow_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    step(async move {
+      println!("[Server] Producing count {}", count);
+      sleep(Duration::from_millis(300)).await;
+
+      send_value(
+        count,
+        detach_shared_session(make_slow_counter_session(count + 1)),
+      )
+    })
+  })
+}
+
+pub fn read_counter_session(
+  name: &'static str,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[{}] Received count: {}", name, count);
+
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+pub fn impatient_session(
+  name: &'static str,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  timeout(
+    Duration::from_millis(100),
+    read_counter_session(name, counter),
+    step(async move {
+      println!("[{}] Gave up waiting for the counter", name);
+
+      terminate()
+    }),
+  )
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let counter = run_shared_session(make_slow_counter_session(0));
+
+  let sessions = vec![
+    read_counter_session("Patient", counter.clone()),
+    impatient_session("Impatient", counter.clone()),
+    timeout(
+      Duration::from_secs(5),
+      read_counter_session("Relaxed", counter.clone()),
+      terminate(),
+    ),
+  ];
+
+  run_session(wait_sessions(sessions, terminate()))
+    .await
+    .unwrap();
+
+  let res = run_session(timeout_or_fail(
+    Duration::from_millis(100),
+    read_counter_session("Strict", counter),
+  ))
+  .await;
+
+  match res {
+    Err(SessionError::TimedOut) => println!("[Strict] Timed out"),
+    res => panic!("expected strict session to time out: {:?}", res),
+  }
+}
@@ -0,0 +1,75 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+use tracing_subscriber::{
+  filter::LevelFilter,
+  fmt::format::FmtSpan,
+};
+
+type SharedCounter = LinearToShared<
+  ExternalChoice<Either<SendValue<u64, Release>, ReceiveValue<u64, Release>>>,
+>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    offer_choice! {
+      Left => {
+        send_value(count, detach_shared_session(make_counter_session(count)))
+      }
+      Right => {
+        receive_value(move |n: u64| {
+          detach_shared_session(make_counter_session(count + n))
+        })
+      }
+    }
+  })
+}
+
+pub fn add_and_read_session(
+  shared: SharedChannel<SharedCounter>
+) -> Session<End>
+{
+  let shared2 = shared.clone();
+
+  acquire_shared_session(shared, move |counter| {
+    choose!(
+      counter,
+      Right,
+      send_value_to(
+        counter,
+        5,
+        release_shared_session(
+          counter,
+          acquire_shared_session(shared2, move |counter| {
+            choose!(
+              counter,
+              Left,
+              receive_value_from(counter, move |count| {
+                println!("[Client] Received count: {}", count);
+
+                release_shared_session(counter, terminate())
+              })
+            )
+          }),
+        ),
+      )
+    )
+  })
+}
+
+// Every step of the session run is shown as a span, with the spans it
+// is nested in printed before it.
+#[tokio::main]
+pub async fn main()
+{
+  tracing_subscriber::fmt()
+    .with_max_level(LevelFilter::TRACE)
+    .with_span_events(FmtSpan::NEW)
+    .init();
+
+  let shared = run_shared_session(make_counter_session(0));
+
+  run_session(add_and_read_session(shared)).await.unwrap();
+}
@@ -0,0 +1,94 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+
+type Greeter = ReceiveValue<
+  String,
+  ExternalChoice<
+    Either<SendValue<String, End>, SendChannel<SendValue<u64, End>, End>>,
+  >,
+>;
+
+pub fn greeter_session(
+  greeting: &'static str,
+  lucky: u64,
+) -> Session<Greeter>
+{
+  receive_value(move |name: String| {
+    offer_choice! {
+      Left => {
+        send_value(format!("{}, {}!", greeting, name), terminate())
+      }
+      Right => {
+        include_session(send_value(lucky, terminate()), |chan| {
+          send_channel_from(chan, terminate())
+        })
+      }
+    }
+  })
+}
+
+pub fn client_session() -> Session<ReceiveChannel<Greeter, End>>
+{
+  receive_channel(|greeter| {
+    send_value_to(
+      greeter,
+      "Alice".to_string(),
+      choose!(
+        greeter,
+        Right,
+        receive_channel_from(greeter, move |lucky| {
+          receive_value_from(lucky, move |number| {
+            println!("[Client] Received lucky number: {}", number);
+
+            wait(lucky, wait(greeter, terminate()))
+          })
+        })
+      ),
+    )
+  })
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let (greeter, recorder) =
+    record_session::<_, Bincode>(greeter_session("Hello", 7));
+
+  run_session(apply_channel(client_session(), greeter))
+    .await
+    .unwrap();
+
+  let transcript = recorder.transcript();
+
+  for entry in &transcript.entries {
+    println!(
+      "[Transcript] {:?} on stream {:?}: {} ({} bytes)",
+      entry.from,
+      entry.stream,
+      entry.step,
+      entry.payload.len()
+    );
+  }
+
+  // The client is played back from the transcript, so a provider that
+  // does not answer the same way is caught without running the client.
+  let res =
+    replay_transcript(greeter_session("Hello", 7), &transcript, Bincode).await;
+
+  println!(
+    "[Replay] same provider: {:?}",
+    res.map_err(|err| err.to_string())
+  );
+
+  let res =
+    replay_transcript(greeter_session("Hello", 8), &transcript, Bincode).await;
+
+  println!(
+    "[Replay] different lucky number: {:?}",
+    res.map_err(|err| err.to_string())
+  );
+}
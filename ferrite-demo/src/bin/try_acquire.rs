@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use ipc_channel::ipc;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+pub fn make_counter_session(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(
+      count,
+      detach_shared_session(make_counter_session(count + 1)),
+    )
+  })
+}
+
+pub fn hold_counter_session(
+  counter: SharedChannel<SharedCounter>
+) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |count| {
+      println!("[Holder] Holding count {}", count);
+
+      step(async move {
+        sleep(Duration::from_millis(300)).await;
+
+        println!("[Holder] Releasing count {}", count);
+
+        release_shared_session(chan, terminate())
+      })
+    })
+  })
+}
+
+pub fn try_counter_session(
+  name: &'static str,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  counter.try_acquire(
+    move |chan| {
+      receive_value_from(chan, move |count| {
+        println!("[{}] Received count: {}", name, count);
+
+        release_shared_session(chan, terminate())
+      })
+    },
+    step(async move {
+      println!("[{}] Counter is busy", name);
+
+      terminate()
+    }),
+  )
+}
+
+pub fn wait_counter_session(
+  name: &'static str,
+  duration: Duration,
+  counter: SharedChannel<SharedCounter>,
+) -> Session<End>
+{
+  counter.acquire_with_timeout(
+    duration,
+    move |chan| {
+      receive_value_from(chan, move |count| {
+        println!("[{}] Received count: {}", name, count);
+
+        release_shared_session(chan, terminate())
+      })
+    },
+    step(async move {
+      println!("[{}] Gave up after {:?}", name, duration);
+
+      terminate()
+    }),
+  )
+}
+
+#[tokio::main]
+pub async fn main()
+{
+  env_logger::init();
+
+  let counter = run_shared_session(make_counter_session(0));
+
+  // The remote copy forwards try-acquires over IPC, and the busy
+  // answer comes back the same way.
+  let (sender, receiver) = ipc::channel().unwrap();
+  sender.send(counter.clone()).unwrap();
+  let remote_counter: SharedChannel<SharedCounter> = receiver.recv().unwrap();
+
+  let holder = tokio::spawn(run_session(hold_counter_session(counter.clone())));
+
+  sleep(Duration::from_millis(100)).await;
+
+  let sessions = vec![
+    try_counter_session("Try", counter.clone()),
+    try_counter_session("RemoteTry", remote_counter.clone()),
+    wait_counter_session("Short", Duration::from_millis(50), counter.clone()),
+    wait_counter_session("Long", Duration::from_secs(5), counter.clone()),
+  ];
+
+  run_session(wait_sessions(sessions, terminate()))
+    .await
+    .unwrap();
+
+  holder.await.unwrap().unwrap();
+
+  run_session(try_counter_session("TryAgain", remote_counter))
+    .await
+    .unwrap();
+}
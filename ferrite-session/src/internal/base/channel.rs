@@ -1,28 +1,43 @@
 use std::{
+  future::Future,
   marker::PhantomData,
   mem,
   ops::DerefMut,
+  pin::Pin,
   sync::{
     Arc,
     Mutex,
   },
+  time::Duration,
 };
 
-use ipc_channel::ipc;
+use futures::stream::{
+  FuturesUnordered,
+  StreamExt,
+};
+use ipc_channel::{
+  ipc,
+  router::ROUTER,
+};
 use serde::{
   self,
   Deserialize,
   Serialize,
 };
-use tokio::{
-  sync::{
-    mpsc,
-    oneshot,
-    Mutex as AsyncMutex,
-  },
-  task,
+use tokio::sync::{
+  mpsc,
+  oneshot,
+  Mutex as AsyncMutex,
 };
 
+use super::{
+  error::{
+    ChannelError,
+    ForwardError,
+  },
+  runtime,
+  transport::Transport,
+};
 use crate::internal::functional::*;
 
 pub struct ReceiverF {}
@@ -35,6 +50,10 @@ pub struct Sender<T>(pub mpsc::UnboundedSender<T>);
 
 pub struct Receiver<T>(pub Arc<AsyncMutex<mpsc::UnboundedReceiver<T>>>);
 
+pub struct BoundedSender<T>(pub mpsc::Sender<T>);
+
+pub struct BoundedReceiver<T>(pub Arc<AsyncMutex<mpsc::Receiver<T>>>);
+
 pub struct SenderOnce<T>(oneshot::Sender<T>);
 
 pub struct ReceiverOnce<T>(oneshot::Receiver<T>);
@@ -59,22 +78,86 @@ pub struct IpcReceiver<T>
   phantom: PhantomData<T>,
 }
 
-// TODO: Define proper error type
-#[derive(Debug)]
-pub struct SendError(pub String);
+pub type ForwardFuture<T> =
+  Pin<Box<dyn Future<Output = Result<T, ForwardError>> + Send>>;
+
+// What a forwarding step leaves behind: the steps that forward the
+// rest of the channel. Like SessionStep, they are returned rather than
+// awaited, and run_forward drives them in a loop, so that forwarding a
+// long recursive protocol does not nest a future for every message.
+pub struct ForwardStep
+{
+  next: Vec<ForwardFuture<ForwardStep>>,
+}
 
-pub trait ForwardChannel: Send + 'static
+pub trait ForwardChannel: Sized + Send + 'static
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  );
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport;
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport;
+}
+
+pub trait ForwardSum: Sized + Send + 'static
+{
+  fn forward_sum_to<Tr>(
+    self,
+    index: u64,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport;
+
+  fn forward_sum_from<Tr>(
+    index: u64,
+    transport: Tr,
+  ) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport;
+}
+
+impl ForwardStep
+{
+  pub fn done() -> ForwardStep
+  {
+    ForwardStep { next: Vec::new() }
+  }
+
+  pub fn next(
+    future: impl Future<Output = Result<ForwardStep, ForwardError>> + Send + 'static
+  ) -> ForwardStep
+  {
+    ForwardStep {
+      next: vec![Box::pin(future)],
+    }
+  }
+
+  pub fn join(
+    mut self,
+    other: ForwardStep,
+  ) -> ForwardStep
+  {
+    self.next.extend(other.next);
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self;
+    self
+  }
+}
+
+pub async fn run_forward(step: ForwardStep) -> Result<(), ForwardError>
+{
+  let mut pending: FuturesUnordered<_> = step.next.into_iter().collect();
+
+  while let Some(step) = pending.next().await {
+    pending.extend(step?.next);
+  }
+
+  Ok(())
 }
 
 pub fn once_channel<T>() -> (SenderOnce<T>, ReceiverOnce<T>)
@@ -94,43 +177,86 @@ pub fn unbounded<T>() -> (Sender<T>, Receiver<T>)
   )
 }
 
+pub fn bounded<T>(capacity: usize) -> (BoundedSender<T>, BoundedReceiver<T>)
+{
+  let (sender, receiver) = mpsc::channel(capacity);
+
+  (
+    BoundedSender(sender),
+    BoundedReceiver(Arc::new(AsyncMutex::new(receiver))),
+  )
+}
+
 impl OpaqueSender
 {
   pub fn send<T>(
     &self,
     val: T,
-  ) where
+  ) -> Result<(), ChannelError>
+  where
     T: for<'de> Deserialize<'de> + Serialize,
   {
     let mut cell = self.0.lock().unwrap();
 
-    let sender1 = mem::take(cell.deref_mut()).unwrap();
+    let sender1 = mem::take(cell.deref_mut()).ok_or_else(|| {
+      ChannelError::ProtocolDesync(String::from(
+        "opaque sender is missing its ipc handle",
+      ))
+    })?;
 
     let sender2 = sender1.to();
 
-    sender2.send(val).unwrap();
+    let res = sender2.send(val);
 
     let _ = mem::replace(cell.deref_mut(), Some(sender2.to_opaque()));
+
+    res.map_err(|err| ChannelError::Serialization(err))
   }
 }
 
 impl OpaqueReceiver
 {
-  pub fn recv<T>(&self) -> Option<T>
+  pub fn recv<T>(&self) -> Result<T, ChannelError>
   where
     T: for<'de> Deserialize<'de> + Serialize,
   {
     let mut cell = self.0.lock().unwrap();
 
-    let receiver1 = mem::take(cell.deref_mut()).unwrap();
+    let receiver1 = mem::take(cell.deref_mut()).ok_or_else(|| {
+      ChannelError::ProtocolDesync(String::from(
+        "opaque receiver is missing its ipc handle",
+      ))
+    })?;
 
     let receiver2 = receiver1.to();
 
-    let val = receiver2.recv().ok();
+    let val = receiver2.recv();
 
     let _ = mem::replace(cell.deref_mut(), Some(receiver2.to_opaque()));
 
-    val
+    Ok(val?)
+  }
+
+  // Hands the ipc handle over to the router thread, which keeps reading
+  // messages into the returned channel until the peer disconnects.
+  pub fn route(&self) -> Result<Receiver<ipc::OpaqueIpcMessage>, ChannelError>
+  {
+    let receiver = self.0.lock().unwrap().take().ok_or_else(|| {
+      ChannelError::ProtocolDesync(String::from(
+        "opaque receiver is missing its ipc handle",
+      ))
+    })?;
+
+    let (sender, incoming) = unbounded();
+
+    ROUTER.add_route(
+      receiver,
+      Box::new(move |message| {
+        let _ = sender.send(message);
+      }),
+    );
+
+    Ok(incoming)
   }
 }
 
@@ -159,7 +285,7 @@ where
   pub fn send(
     &self,
     data: T,
-  )
+  ) -> Result<(), ChannelError>
   {
     self.sender.send(data)
   }
@@ -169,19 +295,19 @@ impl<T> IpcReceiver<T>
 where
   T: for<'de> Deserialize<'de> + Serialize,
 {
-  pub fn recv(&self) -> Option<T>
+  pub fn recv(&self) -> Result<T, ChannelError>
   {
     self.receiver.recv()
   }
 }
 
-pub fn ipc_channel<T>() -> (IpcSender<T>, IpcReceiver<T>)
+pub fn ipc_channel<T>() -> Result<(IpcSender<T>, IpcReceiver<T>), ChannelError>
 where
   IpcReceiver<T>: Send,
 {
-  let (sender, receiver) = opaque_channel();
+  let (sender, receiver) = opaque_channel()?;
 
-  (
+  Ok((
     IpcSender {
       sender,
       phantom: PhantomData,
@@ -190,17 +316,17 @@ where
       receiver,
       phantom: PhantomData,
     },
-  )
+  ))
 }
 
-pub fn opaque_channel() -> (OpaqueSender, OpaqueReceiver)
+pub fn opaque_channel() -> Result<(OpaqueSender, OpaqueReceiver), ChannelError>
 {
-  let (sender, receiver) = ipc::channel::<()>().unwrap();
+  let (sender, receiver) = ipc::channel::<()>()?;
 
-  (
+  Ok((
     OpaqueSender(Arc::new(Mutex::new(Some(sender.to_opaque())))),
     OpaqueReceiver(Arc::new(Mutex::new(Some(receiver.to_opaque())))),
-  )
+  ))
 }
 
 impl<T> Clone for Sender<T>
@@ -224,12 +350,60 @@ impl<T> Sender<T>
   pub fn send(
     &self,
     msg: T,
-  ) -> Result<(), SendError>
+  ) -> Result<(), ChannelError>
   {
-    self
-      .0
-      .send(msg)
-      .map_err(|_| SendError(String::from("failed to send")))
+    self.0.send(msg).map_err(|_| ChannelError::Closed)
+  }
+}
+
+impl<T> Clone for BoundedSender<T>
+{
+  fn clone(&self) -> BoundedSender<T>
+  {
+    BoundedSender(self.0.clone())
+  }
+}
+
+impl<T> Clone for BoundedReceiver<T>
+{
+  fn clone(&self) -> BoundedReceiver<T>
+  {
+    BoundedReceiver(self.0.clone())
+  }
+}
+
+impl<T> BoundedSender<T>
+{
+  pub async fn send(
+    &self,
+    msg: T,
+  ) -> Result<(), ChannelError>
+  {
+    self.0.send(msg).await.map_err(|_| ChannelError::Closed)
+  }
+
+  // Send without waiting for room in the channel, and hand the message
+  // back if there is none.
+  pub fn try_send(
+    &self,
+    msg: T,
+  ) -> Result<Option<T>, ChannelError>
+  {
+    match self.0.try_send(msg) {
+      Ok(()) => Ok(None),
+      Err(mpsc::error::TrySendError::Full(msg)) => Ok(Some(msg)),
+      Err(mpsc::error::TrySendError::Closed(_)) => Err(ChannelError::Closed),
+    }
+  }
+}
+
+impl<T> BoundedReceiver<T>
+{
+  pub async fn recv(&self) -> Option<T>
+  {
+    runtime::yield_now().await;
+
+    self.0.lock().await.recv().await
   }
 }
 
@@ -237,6 +411,8 @@ impl<T> Receiver<T>
 {
   pub async fn recv(&self) -> Option<T>
   {
+    runtime::yield_now().await;
+
     self.0.lock().await.recv().await
   }
 }
@@ -246,20 +422,45 @@ impl<T> SenderOnce<T>
   pub fn send(
     self,
     msg: T,
-  ) -> Result<(), SendError>
+  ) -> Result<(), ChannelError>
   {
-    self
-      .0
-      .send(msg)
-      .map_err(|_| SendError(String::from("failed to send")))
+    self.0.send(msg).map_err(|_| ChannelError::Closed)
+  }
+
+  pub fn is_closed(&self) -> bool
+  {
+    self.0.is_closed()
   }
 }
 
 impl<T> ReceiverOnce<T>
 {
-  pub async fn recv(self) -> Result<T, oneshot::error::RecvError>
+  // Receiving is where sessions wait on each other, so it is also
+  // where the simulation scheduler gets to reorder them.
+  pub async fn recv(self) -> Result<T, ChannelError>
+  {
+    runtime::yield_now().await;
+
+    self.0.await.map_err(|_| ChannelError::Closed)
+  }
+
+  pub async fn recv_timeout(
+    mut self,
+    duration: Duration,
+  ) -> Result<Option<T>, ChannelError>
   {
-    self.0.await
+    runtime::yield_now().await;
+
+    match runtime::timeout(duration, &mut self.0).await {
+      Ok(res) => res.map(Some).map_err(|_| ChannelError::Closed),
+      Err(_) => {
+        // Close the channel so that the sender can tell we are gone,
+        // but still take a value that raced with the deadline.
+        self.0.close();
+
+        Ok(self.0.try_recv().ok())
+      }
+    }
   }
 
   pub async fn close(mut self)
@@ -270,19 +471,21 @@ impl<T> ReceiverOnce<T>
 
 impl ForwardChannel for ()
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    _: OpaqueSender,
-    _: OpaqueReceiver,
-  )
+    _: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
+    ForwardStep::done()
   }
 
-  fn forward_from(
-    _: OpaqueSender,
-    _: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(_: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
+    Box::pin(async { Ok(((), ForwardStep::done())) })
   }
 }
 
@@ -290,39 +493,48 @@ impl<T> ForwardChannel for SenderOnce<T>
 where
   T: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    task::spawn_blocking(move || {
-      receiver.recv::<()>().unwrap();
+    ForwardStep::next(async move {
+      transport
+        .recv::<()>("ack")
+        .await
+        .map_err(ForwardError::new::<Self>)?;
+
+      let (payload, rest) = T::forward_from(transport).await?;
 
-      let payload = T::forward_from(sender, receiver);
+      self.send(payload).map_err(ForwardError::new::<Self>)?;
 
-      self.send(payload).unwrap();
-    });
+      Ok(rest)
+    })
   }
 
-  fn forward_from(
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let (sender2, receiver2) = once_channel();
+    Box::pin(async move {
+      let (sender, receiver) = once_channel::<T>();
 
-    task::spawn(async move {
-      let payload: T = receiver2.recv().await.unwrap();
+      let rest = ForwardStep::next(async move {
+        let payload =
+          receiver.recv().await.map_err(ForwardError::new::<Self>)?;
 
-      task::spawn_blocking(move || {
-        sender1.send(());
+        transport
+          .send("ack", ())
+          .await
+          .map_err(ForwardError::new::<Self>)?;
 
-        payload.forward_to(sender1, receiver1);
+        Ok(payload.forward_to(transport))
       });
-    });
 
-    sender2
+      Ok((sender, rest))
+    })
   }
 }
 
@@ -330,39 +542,47 @@ impl<T> ForwardChannel for ReceiverOnce<T>
 where
   T: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    task::spawn(async move {
-      let channel = self.recv().await.unwrap();
+    ForwardStep::next(async move {
+      let channel = self.recv().await.map_err(ForwardError::new::<Self>)?;
 
-      task::spawn_blocking(move || {
-        sender1.send(());
+      transport
+        .send("ack", ())
+        .await
+        .map_err(ForwardError::new::<Self>)?;
 
-        channel.forward_to(sender1, receiver1);
-      });
-    });
+      Ok(channel.forward_to(transport))
+    })
   }
 
-  fn forward_from(
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let (sender2, receiver2) = once_channel();
+    Box::pin(async move {
+      let (sender, receiver) = once_channel::<T>();
+
+      let rest = ForwardStep::next(async move {
+        transport
+          .recv::<()>("ack")
+          .await
+          .map_err(ForwardError::new::<Self>)?;
 
-    task::spawn_blocking(move || {
-      receiver1.recv::<()>().unwrap();
+        let (channel, rest) = T::forward_from(transport).await?;
 
-      let channel = T::forward_from(sender1, receiver1);
+        sender.send(channel).map_err(ForwardError::new::<Self>)?;
 
-      sender2.send(channel).unwrap();
-    });
+        Ok(rest)
+      });
 
-    receiver2
+      Ok((receiver, rest))
+    })
   }
 }
 
@@ -372,31 +592,91 @@ where
   T: Serialize + for<'de> Deserialize<'de>,
   C: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    ForwardStep::next(async move {
+      let (Value(payload), channel) = self;
+
+      transport
+        .send("value", payload)
+        .await
+        .map_err(ForwardError::new::<Value<T>>)?;
+
+      Ok(channel.forward_to(transport))
+    })
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let (Value(payload), channel) = self;
+    Box::pin(async move {
+      let payload = transport
+        .recv("value")
+        .await
+        .map_err(ForwardError::new::<Value<T>>)?;
 
-    task::spawn_blocking(move || {
-      sender1.send(payload);
+      let (channel, rest) = C::forward_from(transport).await?;
 
-      channel.forward_to(sender1, receiver1)
-    });
+      Ok(((Value(payload), channel), rest))
+    })
   }
+}
 
-  fn forward_from(
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  ) -> Self
+impl<A, B> ForwardChannel for (ReceiverOnce<A>, B)
+where
+  A: ForwardChannel,
+  B: ForwardChannel,
+{
+  fn forward_to<Tr>(
+    self,
+    transport1: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    ForwardStep::next(async move {
+      let (channel, cont) = self;
+
+      let (transport2, transport3) = transport1
+        .pair()
+        .map_err(ForwardError::new::<ReceiverOnce<A>>)?;
+
+      transport1
+        .send("channel", transport3)
+        .await
+        .map_err(ForwardError::new::<ReceiverOnce<A>>)?;
+
+      Ok(
+        channel
+          .forward_to(transport2)
+          .join(cont.forward_to(transport1)),
+      )
+    })
+  }
+
+  fn forward_from<Tr>(transport1: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let payload = receiver1.recv().unwrap();
+    Box::pin(async move {
+      let transport2 = transport1
+        .recv::<Tr>("channel")
+        .await
+        .map_err(ForwardError::new::<ReceiverOnce<A>>)?;
 
-    let channel = C::forward_from(sender1, receiver1);
+      let (channel, rest1) =
+        <ReceiverOnce<A>>::forward_from(transport2).await?;
 
-    (Value(payload), channel)
+      let (cont, rest2) = B::forward_from(transport1).await?;
+
+      Ok(((channel, cont), rest1.join(rest2)))
+    })
   }
 }
 
@@ -406,21 +686,25 @@ where
   F: TypeApp<X, Applied = T>,
   T: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.get_applied().forward_to(sender, receiver)
+    self.get_applied().forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    wrap_type_app(T::forward_from(sender, receiver))
+    Box::pin(async move {
+      let (applied, rest) = T::forward_from(transport).await?;
+
+      Ok((wrap_type_app(applied), rest))
+    })
   }
 }
 
@@ -431,78 +715,189 @@ where
   Row: SumApp<F, Applied = T>,
   T: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.get_sum().forward_to(sender, receiver)
+    self.get_sum().forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    wrap_sum_app(T::forward_from(sender, receiver))
+    Box::pin(async move {
+      let (sum, rest) = T::forward_from(transport).await?;
+
+      Ok((wrap_sum_app(sum), rest))
+    })
   }
 }
 
 impl<A, B> ForwardChannel for Sum<A, B>
 where
   A: ForwardChannel,
-  B: ForwardChannel,
+  B: ForwardSum,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    self.forward_sum_to(0, transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let index = transport
+        .recv::<u64>("choice")
+        .await
+        .map_err(ForwardError::new::<Self>)?;
+
+      Self::forward_sum_from(index, transport).await
+    })
+  }
+}
+
+// A choice is sent as the index of the selected branch in a single
+// frame, rather than as a chain of left/right tags.
+impl<A, B> ForwardSum for Sum<A, B>
+where
+  A: ForwardChannel,
+  B: ForwardSum,
+{
+  fn forward_sum_to<Tr>(
+    self,
+    index: u64,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
     match self {
-      Sum::Inl(a) => {
-        sender1.send(true);
+      Sum::Inl(a) => ForwardStep::next(async move {
+        transport
+          .send("choice", index)
+          .await
+          .map_err(ForwardError::new::<Self>)?;
+
+        Ok(a.forward_to(transport))
+      }),
+      Sum::Inr(b) => b.forward_sum_to(index + 1, transport),
+    }
+  }
 
-        a.forward_to(sender1, receiver1)
-      }
-      Sum::Inr(b) => {
-        sender1.send(false);
+  fn forward_sum_from<Tr>(
+    index: u64,
+    transport: Tr,
+  ) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      if index == 0 {
+        let (a, rest) = A::forward_from(transport).await?;
+
+        Ok((Sum::Inl(a), rest))
+      } else {
+        let (b, rest) = B::forward_sum_from(index - 1, transport).await?;
 
-        b.forward_to(sender1, receiver1)
+        Ok((Sum::Inr(b), rest))
       }
-    }
+    })
   }
+}
 
-  fn forward_from(
-    sender1: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  ) -> Self
+impl<Row, F, T> ForwardSum for AppSum<Row, F>
+where
+  F: TyCon,
+  F: Send + 'static,
+  Row: SumApp<F, Applied = T>,
+  T: ForwardSum,
+{
+  fn forward_sum_to<Tr>(
+    self,
+    index: u64,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    if receiver1.recv().unwrap() {
-      Sum::Inl(A::forward_from(sender1, receiver1))
-    } else {
-      Sum::Inr(B::forward_from(sender1, receiver1))
-    }
+    self.get_sum().forward_sum_to(index, transport)
+  }
+
+  fn forward_sum_from<Tr>(
+    index: u64,
+    transport: Tr,
+  ) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let (sum, rest) = T::forward_sum_from(index, transport).await?;
+
+      Ok((wrap_sum_app(sum), rest))
+    })
+  }
+}
+
+impl ForwardSum for Bottom
+{
+  fn forward_sum_to<Tr>(
+    self,
+    _: u64,
+    _: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    match self {}
+  }
+
+  fn forward_sum_from<Tr>(
+    _: u64,
+    _: Tr,
+  ) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      Err(ForwardError::new::<Self>(ChannelError::ProtocolDesync(
+        "choice index out of range".to_string(),
+      )))
+    })
   }
 }
 
 impl ForwardChannel for Bottom
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    _: OpaqueSender,
-    _: OpaqueReceiver,
-  )
+    _: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
     match self {}
   }
 
-  fn forward_from(
-    _: OpaqueSender,
-    receiver1: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    receiver1.recv().unwrap()
+    Box::pin(async move {
+      match transport
+        .recv::<Bottom>("choice")
+        .await
+        .map_err(ForwardError::new::<Self>)? {}
+    })
   }
 }
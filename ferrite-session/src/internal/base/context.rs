@@ -67,6 +67,9 @@ where
 
   type Target: Context;
 
+  // The position of the slot in the context, counting from zero.
+  fn slot_index() -> usize;
+
   fn extract_source(
     channels: C::Endpoints
   ) -> (A1::Endpoint, <Self::Deleted as Context>::Endpoints);
@@ -86,6 +89,11 @@ where
   type Deleted = C;
   type Target = (A2, C);
 
+  fn slot_index() -> usize
+  {
+    0
+  }
+
   fn extract_source(
     ctx: (A1::Endpoint, C::Endpoints)
   ) -> (A1::Endpoint, C::Endpoints)
@@ -113,6 +121,11 @@ where
   type Deleted = (B, N::Deleted);
   type Target = (B, N::Target);
 
+  fn slot_index() -> usize
+  {
+    N::slot_index() + 1
+  }
+
   fn extract_source(
     (p, r1): (B::Endpoint, C::Endpoints)
   ) -> (
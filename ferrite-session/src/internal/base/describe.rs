@@ -0,0 +1,536 @@
+use std::{
+  any::type_name,
+  collections::{
+    BTreeMap,
+    HashMap,
+  },
+  fmt,
+};
+
+use serde::{
+  Deserialize,
+  Serialize,
+};
+
+// A runtime description of a protocol, built from its type. Recursion
+// variables are de Bruijn indices, with `Var(0)` referring to the
+// innermost enclosing `Rec`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProtocolDesc
+{
+  End,
+  SendValue(String, Box<ProtocolDesc>),
+  ReceiveValue(String, Box<ProtocolDesc>),
+  SendChannel(Box<ProtocolDesc>, Box<ProtocolDesc>),
+  ReceiveChannel(Box<ProtocolDesc>, Box<ProtocolDesc>),
+  ExternalChoice(Vec<ProtocolDesc>),
+  InternalChoice(Vec<ProtocolDesc>),
+  Rec(Box<ProtocolDesc>),
+  Var(usize),
+  LinearToShared(Box<ProtocolDesc>),
+  Release,
+  // A wrapped protocol is only expanded at its outermost occurrence.
+  // Occurrences within its own definition are left as references to
+  // its name, as recursive wrappers would otherwise never end.
+  Wrap(String, Option<Box<ProtocolDesc>>),
+}
+
+// How closely the protocol offered by a peer has to match the one that
+// is expected locally. Subtype allows the peer to offer a protocol that
+// can safely stand in for the expected one, such as an external choice
+// with extra branches appended at the end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compatibility
+{
+  Exact,
+  Subtype,
+}
+
+// Tracks the wrappers that are being expanded.
+#[derive(Default)]
+pub struct DescribeContext
+{
+  wrappers: Vec<String>,
+}
+
+pub trait Describe
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc;
+}
+
+/*
+   The name a payload type is described by. Descriptions are compared
+   across processes and builds, so the name is given by hand rather
+   than taken from std::any::type_name, which differs between compilers
+   and leaves types of the same name in different modules apart only by
+   their paths.
+*/
+
+pub trait DescribeValue
+{
+  fn value_name() -> String;
+}
+
+pub trait DescribeRow
+{
+  fn describe_row(
+    ctx: &mut DescribeContext,
+    branches: &mut Vec<ProtocolDesc>,
+  );
+}
+
+pub fn describe<A>() -> ProtocolDesc
+where
+  A: Describe,
+{
+  A::describe_in(&mut DescribeContext::default())
+}
+
+impl DescribeRow for ()
+{
+  fn describe_row(
+    _: &mut DescribeContext,
+    _: &mut Vec<ProtocolDesc>,
+  )
+  {
+  }
+}
+
+impl<A, R> DescribeRow for (A, R)
+where
+  A: Describe,
+  R: DescribeRow,
+{
+  fn describe_row(
+    ctx: &mut DescribeContext,
+    branches: &mut Vec<ProtocolDesc>,
+  )
+  {
+    branches.push(A::describe_in(ctx));
+    R::describe_row(ctx, branches);
+  }
+}
+
+pub fn describe_row<Row>(ctx: &mut DescribeContext) -> Vec<ProtocolDesc>
+where
+  Row: DescribeRow,
+{
+  let mut branches = Vec::new();
+
+  Row::describe_row(ctx, &mut branches);
+
+  branches
+}
+
+impl DescribeContext
+{
+  pub fn describe_wrapper<A>(
+    &mut self,
+    name: &str,
+  ) -> ProtocolDesc
+  where
+    A: Describe,
+  {
+    let name = name.to_string();
+
+    if self.wrappers.contains(&name) {
+      return ProtocolDesc::Wrap(name, None);
+    }
+
+    self.wrappers.push(name.clone());
+
+    let unwrap = A::describe_in(self);
+
+    self.wrappers.pop();
+
+    ProtocolDesc::Wrap(name, Some(Box::new(unwrap)))
+  }
+}
+
+macro_rules! describe_value {
+  ( $( $t:ty ),* ) => {
+    $(
+      impl DescribeValue for $t
+      {
+        fn value_name() -> String
+        {
+          stringify!($t).to_string()
+        }
+      }
+    )*
+  };
+}
+
+describe_value!(
+  (),
+  bool,
+  char,
+  u8,
+  u16,
+  u32,
+  u64,
+  u128,
+  usize,
+  i8,
+  i16,
+  i32,
+  i64,
+  i128,
+  isize,
+  f32,
+  f64,
+  String
+);
+
+impl<T> DescribeValue for Box<T>
+where
+  T: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    T::value_name()
+  }
+}
+
+impl<T> DescribeValue for Option<T>
+where
+  T: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    format!("Option<{}>", T::value_name())
+  }
+}
+
+impl<T, E> DescribeValue for Result<T, E>
+where
+  T: DescribeValue,
+  E: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    format!("Result<{}, {}>", T::value_name(), E::value_name())
+  }
+}
+
+impl<T> DescribeValue for Vec<T>
+where
+  T: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    format!("Vec<{}>", T::value_name())
+  }
+}
+
+impl<K, V> DescribeValue for HashMap<K, V>
+where
+  K: DescribeValue,
+  V: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    format!("HashMap<{}, {}>", K::value_name(), V::value_name())
+  }
+}
+
+impl<K, V> DescribeValue for BTreeMap<K, V>
+where
+  K: DescribeValue,
+  V: DescribeValue,
+{
+  fn value_name() -> String
+  {
+    format!("BTreeMap<{}, {}>", K::value_name(), V::value_name())
+  }
+}
+
+macro_rules! describe_tuple {
+  ( $( $t:ident ),* ) => {
+    impl< $( $t ),* > DescribeValue for ( $( $t, )* )
+    where
+      $( $t: DescribeValue, )*
+    {
+      fn value_name() -> String
+      {
+        let names: Vec<String> = vec![ $( $t::value_name() ),* ];
+
+        format!("({})", names.join(", "))
+      }
+    }
+  };
+}
+
+describe_tuple!(A, B);
+describe_tuple!(A, B, C);
+describe_tuple!(A, B, C, D);
+
+// The name of a type with the module paths left out, including the
+// ones of its type arguments. Only meant for logs and traces, as two
+// types can end up with the same label.
+pub fn type_label<T>() -> String
+{
+  let name = type_name::<T>();
+
+  let mut label = String::with_capacity(name.len());
+
+  let mut segment = 0;
+
+  let mut chars = name.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if c == ':' && chars.peek() == Some(&':') {
+      chars.next();
+      label.truncate(segment);
+    } else {
+      label.push(c);
+
+      if !(c.is_alphanumeric() || c == '_') {
+        segment = label.len();
+      }
+    }
+  }
+
+  label
+}
+
+impl ProtocolDesc
+{
+  // Whether a peer offering this protocol can be used by a client that
+  // expects the other protocol.
+  pub fn is_compatible_with(
+    &self,
+    expected: &ProtocolDesc,
+    compatibility: Compatibility,
+  ) -> bool
+  {
+    match compatibility {
+      Compatibility::Exact => self == expected,
+      Compatibility::Subtype => self.is_subtype_of(expected),
+    }
+  }
+
+  /*
+     Subtyping follows the direction in which each part of the protocol
+     is used. The provider of an external choice may offer more
+     branches than the client knows of, while the provider of an
+     internal choice may pick from fewer. A channel received by the
+     provider is used the other way round. Since choices are sent as
+     branch indices, extra branches are only safe at the end.
+  */
+  pub fn is_subtype_of(
+    &self,
+    other: &ProtocolDesc,
+  ) -> bool
+  {
+    use ProtocolDesc::*;
+
+    match (self, other) {
+      // Wrappers are transparent on the wire.
+      (Wrap(_, Some(a)), b) if !b.is_wrap() => a.is_subtype_of(b),
+      (a, Wrap(_, Some(b))) if !a.is_wrap() => a.is_subtype_of(b),
+      (Wrap(_, Some(a)), Wrap(_, Some(b))) => a.is_subtype_of(b),
+      (Wrap(a, _), Wrap(b, _)) => a == b,
+      (End, End) | (Release, Release) => true,
+      (Var(i), Var(j)) => i == j,
+      (SendValue(t1, a1), SendValue(t2, a2))
+      | (ReceiveValue(t1, a1), ReceiveValue(t2, a2)) => {
+        t1 == t2 && a1.is_subtype_of(a2)
+      }
+      (SendChannel(p1, q1), SendChannel(p2, q2)) => {
+        p1.is_subtype_of(p2) && q1.is_subtype_of(q2)
+      }
+      (ReceiveChannel(p1, q1), ReceiveChannel(p2, q2)) => {
+        p2.is_subtype_of(p1) && q1.is_subtype_of(q2)
+      }
+      (ExternalChoice(bs1), ExternalChoice(bs2)) => {
+        bs1.len() >= bs2.len()
+          && bs1.iter().zip(bs2).all(|(b1, b2)| b1.is_subtype_of(b2))
+      }
+      (InternalChoice(bs1), InternalChoice(bs2)) => {
+        bs1.len() <= bs2.len()
+          && bs1.iter().zip(bs2).all(|(b1, b2)| b1.is_subtype_of(b2))
+      }
+      (Rec(a), Rec(b)) | (LinearToShared(a), LinearToShared(b)) => {
+        a.is_subtype_of(b)
+      }
+      _ => false,
+    }
+  }
+
+  fn is_wrap(&self) -> bool
+  {
+    matches!(self, ProtocolDesc::Wrap(..))
+  }
+
+  // Replace the recursion variables that are free below the given
+  // number of enclosing Rec with the closed protocols they are bound to
+  // in the context, innermost first. Wrapped protocols are described
+  // on their own, so they are left as they are.
+  pub fn close(
+    self,
+    depth: usize,
+    context: &[ProtocolDesc],
+  ) -> ProtocolDesc
+  {
+    use ProtocolDesc::*;
+
+    let close = |a: Box<ProtocolDesc>, depth| Box::new(a.close(depth, context));
+
+    let close_all = |bs: Vec<ProtocolDesc>| {
+      bs.into_iter().map(|b| b.close(depth, context)).collect()
+    };
+
+    match self {
+      Var(i) if i >= depth => match context.get(i - depth) {
+        Some(a) => a.clone(),
+        None => Var(i - context.len()),
+      },
+      SendValue(t, a) => SendValue(t, close(a, depth)),
+      ReceiveValue(t, a) => ReceiveValue(t, close(a, depth)),
+      SendChannel(a, b) => SendChannel(close(a, depth), close(b, depth)),
+      ReceiveChannel(a, b) => ReceiveChannel(close(a, depth), close(b, depth)),
+      ExternalChoice(bs) => ExternalChoice(close_all(bs)),
+      InternalChoice(bs) => InternalChoice(close_all(bs)),
+      Rec(a) => Rec(close(a, depth + 1)),
+      LinearToShared(a) => LinearToShared(close(a, depth)),
+      desc => desc,
+    }
+  }
+}
+
+/*
+   Protocols are printed in the notation used by the typing rules:
+
+     End                   1
+     SendValue<T, A>       T ∧ A
+     ReceiveValue<T, A>    T ⊃ A
+     SendChannel<A, B>     A ⊗ B
+     ReceiveChannel<A, B>  A ⊸ B
+     ExternalChoice<..>    &{ 0: A, 1: B, .. }
+     InternalChoice<..>    ⊕{ 0: A, 1: B, .. }
+     Rec<F>                μX. F
+     LinearToShared<F>     ↑F
+     Release               ↓
+     Wrap<T>               (T = A)
+*/
+
+impl ProtocolDesc
+{
+  fn is_atomic(&self) -> bool
+  {
+    matches!(
+      self,
+      ProtocolDesc::End
+        | ProtocolDesc::ExternalChoice(_)
+        | ProtocolDesc::InternalChoice(_)
+        | ProtocolDesc::Var(_)
+        | ProtocolDesc::Release
+        | ProtocolDesc::Wrap(..)
+    )
+  }
+
+  fn write(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+    depth: usize,
+  ) -> fmt::Result
+  {
+    match self {
+      ProtocolDesc::End => write!(f, "1"),
+      ProtocolDesc::SendValue(t, a) => {
+        write!(f, "{} ∧ ", t)?;
+        a.write(f, depth)
+      }
+      ProtocolDesc::ReceiveValue(t, a) => {
+        write!(f, "{} ⊃ ", t)?;
+        a.write(f, depth)
+      }
+      ProtocolDesc::SendChannel(a, b) => {
+        a.write_operand(f, depth)?;
+        write!(f, " ⊗ ")?;
+        b.write(f, depth)
+      }
+      ProtocolDesc::ReceiveChannel(a, b) => {
+        a.write_operand(f, depth)?;
+        write!(f, " ⊸ ")?;
+        b.write(f, depth)
+      }
+      ProtocolDesc::ExternalChoice(branches) => {
+        write_branches(f, "&", branches, depth)
+      }
+      ProtocolDesc::InternalChoice(branches) => {
+        write_branches(f, "⊕", branches, depth)
+      }
+      ProtocolDesc::Rec(a) => {
+        write!(f, "μX{}. ", depth)?;
+        a.write(f, depth + 1)
+      }
+      ProtocolDesc::Var(i) => {
+        if *i < depth {
+          write!(f, "X{}", depth - 1 - i)
+        } else {
+          write!(f, "X?{}", i - depth)
+        }
+      }
+      ProtocolDesc::LinearToShared(a) => {
+        write!(f, "↑")?;
+        a.write_operand(f, depth)
+      }
+      ProtocolDesc::Release => write!(f, "↓"),
+      ProtocolDesc::Wrap(name, None) => write!(f, "{}", name),
+      ProtocolDesc::Wrap(name, Some(a)) => {
+        write!(f, "({} = ", name)?;
+        a.write(f, depth)?;
+        write!(f, ")")
+      }
+    }
+  }
+
+  fn write_operand(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+    depth: usize,
+  ) -> fmt::Result
+  {
+    if self.is_atomic() {
+      self.write(f, depth)
+    } else {
+      write!(f, "(")?;
+      self.write(f, depth)?;
+      write!(f, ")")
+    }
+  }
+}
+
+fn write_branches(
+  f: &mut fmt::Formatter<'_>,
+  symbol: &str,
+  branches: &[ProtocolDesc],
+  depth: usize,
+) -> fmt::Result
+{
+  write!(f, "{}{{ ", symbol)?;
+
+  for (i, branch) in branches.iter().enumerate() {
+    if i > 0 {
+      write!(f, ", ")?;
+    }
+
+    write!(f, "{}: ", i)?;
+    branch.write(f, depth)?;
+  }
+
+  write!(f, " }}")
+}
+
+impl fmt::Display for ProtocolDesc
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    self.write(f, 0)
+  }
+}
@@ -0,0 +1,204 @@
+use std::{
+  any::type_name,
+  error::Error,
+  fmt,
+  io,
+};
+
+use ipc_channel::ipc;
+
+use super::{
+  describe::ProtocolDesc,
+  runtime,
+};
+
+#[derive(Debug)]
+pub enum ChannelError
+{
+  Closed,
+  Serialization(Box<dyn Error + Send + Sync>),
+  Disconnected,
+  Io(io::Error),
+  ProtocolDesync(String),
+  UnexpectedStep
+  {
+    expected: String,
+    found: String,
+  },
+  IncompatibleProtocol
+  {
+    expected: ProtocolDesc,
+    found: ProtocolDesc,
+  },
+}
+
+#[derive(Debug)]
+pub struct ForwardError
+{
+  pub step: &'static str,
+  pub error: ChannelError,
+}
+
+#[derive(Debug)]
+pub enum SessionError
+{
+  PeerDropped,
+  PeerPanicked,
+  TimedOut,
+  AcquireRefused,
+  TransportFailed(ChannelError),
+}
+
+impl fmt::Display for ChannelError
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    match self {
+      ChannelError::Closed => write!(f, "channel closed"),
+      ChannelError::Serialization(err) => {
+        write!(f, "failed to serialize ipc message: {}", err)
+      }
+      ChannelError::Disconnected => write!(f, "ipc channel disconnected"),
+      ChannelError::Io(err) => write!(f, "ipc channel io error: {}", err),
+      ChannelError::ProtocolDesync(reason) => {
+        write!(f, "protocol out of sync: {}", reason)
+      }
+      ChannelError::UnexpectedStep { expected, found } => {
+        write!(
+          f,
+          "protocol out of sync: expected {} step, got {}",
+          expected, found
+        )
+      }
+      ChannelError::IncompatibleProtocol { expected, found } => {
+        write!(
+          f,
+          "incompatible protocol: expected {}, but peer offers {}",
+          expected, found
+        )
+      }
+    }
+  }
+}
+
+impl Error for ChannelError
+{
+  fn source(&self) -> Option<&(dyn Error + 'static)>
+  {
+    match self {
+      ChannelError::Serialization(err) => Some(err.as_ref()),
+      ChannelError::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl ForwardError
+{
+  pub fn new<P>(error: ChannelError) -> ForwardError
+  {
+    ForwardError {
+      step: type_name::<P>(),
+      error,
+    }
+  }
+}
+
+impl fmt::Display for ForwardError
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    write!(f, "failed to forward {}: {}", self.step, self.error)
+  }
+}
+
+impl Error for ForwardError
+{
+  fn source(&self) -> Option<&(dyn Error + 'static)>
+  {
+    Some(&self.error)
+  }
+}
+
+impl fmt::Display for SessionError
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    match self {
+      SessionError::PeerDropped => {
+        write!(f, "session peer dropped its endpoint")
+      }
+      SessionError::PeerPanicked => write!(f, "session peer panicked"),
+      SessionError::TimedOut => write!(f, "session step timed out"),
+      SessionError::AcquireRefused => {
+        write!(f, "shared session refused to be acquired")
+      }
+      SessionError::TransportFailed(err) => {
+        write!(f, "session transport failed: {}", err)
+      }
+    }
+  }
+}
+
+impl Error for SessionError
+{
+  fn source(&self) -> Option<&(dyn Error + 'static)>
+  {
+    match self {
+      SessionError::TransportFailed(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for ChannelError
+{
+  fn from(err: io::Error) -> Self
+  {
+    ChannelError::Io(err)
+  }
+}
+
+impl From<ipc::IpcError> for ChannelError
+{
+  fn from(err: ipc::IpcError) -> Self
+  {
+    match err {
+      ipc::IpcError::Bincode(err) => ChannelError::Serialization(err),
+      ipc::IpcError::Io(err) => ChannelError::Io(err),
+      ipc::IpcError::Disconnected => ChannelError::Disconnected,
+    }
+  }
+}
+
+impl From<ChannelError> for SessionError
+{
+  fn from(err: ChannelError) -> Self
+  {
+    match err {
+      ChannelError::Closed => SessionError::PeerDropped,
+      err => SessionError::TransportFailed(err),
+    }
+  }
+}
+
+impl From<runtime::JoinError> for SessionError
+{
+  fn from(err: runtime::JoinError) -> Self
+  {
+    if err.is_panic() {
+      SessionError::PeerPanicked
+    } else {
+      SessionError::PeerDropped
+    }
+  }
+}
@@ -0,0 +1,125 @@
+use serde::{
+  de::DeserializeOwned,
+  Serialize,
+};
+
+use super::error::ChannelError;
+
+pub trait WireFormat: Copy + Send + Sync + 'static
+{
+  const NAME: &'static str;
+
+  fn encode<T>(val: &T) -> Result<Vec<u8>, ChannelError>
+  where
+    T: Serialize;
+
+  fn decode<T>(bytes: &[u8]) -> Result<T, ChannelError>
+  where
+    T: DeserializeOwned;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+impl WireFormat for Bincode
+{
+  const NAME: &'static str = "bincode";
+
+  fn encode<T>(val: &T) -> Result<Vec<u8>, ChannelError>
+  where
+    T: Serialize,
+  {
+    bincode::serialize(val).map_err(|err| ChannelError::Serialization(err))
+  }
+
+  fn decode<T>(bytes: &[u8]) -> Result<T, ChannelError>
+  where
+    T: DeserializeOwned,
+  {
+    bincode::deserialize(bytes).map_err(|err| ChannelError::Serialization(err))
+  }
+}
+
+#[cfg(feature = "json")]
+impl WireFormat for Json
+{
+  const NAME: &'static str = "json";
+
+  fn encode<T>(val: &T) -> Result<Vec<u8>, ChannelError>
+  where
+    T: Serialize,
+  {
+    serde_json::to_vec(val)
+      .map_err(|err| ChannelError::Serialization(err.into()))
+  }
+
+  fn decode<T>(bytes: &[u8]) -> Result<T, ChannelError>
+  where
+    T: DeserializeOwned,
+  {
+    serde_json::from_slice(bytes)
+      .map_err(|err| ChannelError::Serialization(err.into()))
+  }
+}
+
+#[cfg(feature = "cbor")]
+impl WireFormat for Cbor
+{
+  const NAME: &'static str = "cbor";
+
+  fn encode<T>(val: &T) -> Result<Vec<u8>, ChannelError>
+  where
+    T: Serialize,
+  {
+    let mut bytes = Vec::new();
+
+    ciborium::ser::into_writer(val, &mut bytes)
+      .map_err(|err| ChannelError::Serialization(err.into()))?;
+
+    Ok(bytes)
+  }
+
+  fn decode<T>(bytes: &[u8]) -> Result<T, ChannelError>
+  where
+    T: DeserializeOwned,
+  {
+    ciborium::de::from_reader(bytes)
+      .map_err(|err| ChannelError::Serialization(err.into()))
+  }
+}
+
+#[cfg(feature = "msgpack")]
+impl WireFormat for MessagePack
+{
+  const NAME: &'static str = "msgpack";
+
+  fn encode<T>(val: &T) -> Result<Vec<u8>, ChannelError>
+  where
+    T: Serialize,
+  {
+    // Struct fields are encoded as maps rather than tuples, so that
+    // the frames stay readable without the Rust type definitions.
+    rmp_serde::to_vec_named(val)
+      .map_err(|err| ChannelError::Serialization(err.into()))
+  }
+
+  fn decode<T>(bytes: &[u8]) -> Result<T, ChannelError>
+  where
+    T: DeserializeOwned,
+  {
+    rmp_serde::from_slice(bytes)
+      .map_err(|err| ChannelError::Serialization(err.into()))
+  }
+}
@@ -0,0 +1,161 @@
+use std::{
+  fmt,
+  sync::{
+    Arc,
+    Mutex,
+  },
+  time::Duration,
+};
+
+// Hooks for observing how a shared session is used. Acquires made
+// through a SharedChannel with metrics attached report how long they
+// waited, while a shared session run with metrics in its
+// SharedSessionConfig reports on its queue and on the linear sessions
+// holding its lock. Every hook does nothing by default, so that a
+// bridge to another metrics backend only needs the ones it records.
+pub trait SharedMetrics: fmt::Debug + Send + Sync + 'static
+{
+  // An acquire finished waiting, and was either granted the lock or
+  // turned down.
+  fn acquire_completed(
+    &self,
+    _wait: Duration,
+    _granted: bool,
+  )
+  {
+  }
+
+  // The number of acquires waiting for the lock has changed.
+  fn queue_depth(
+    &self,
+    _depth: usize,
+  )
+  {
+  }
+
+  // An acquire was turned down by the shared session, because the
+  // queue was full, the session was busy or shutting down.
+  fn acquire_refused(&self) {}
+
+  // A linear session was given the lock, with the number of linear
+  // sessions now holding a lock.
+  fn lock_acquired(
+    &self,
+    _active: usize,
+  )
+  {
+  }
+
+  // A linear session released the lock after holding it for the given
+  // duration.
+  fn lock_released(
+    &self,
+    _held: Duration,
+    _active: usize,
+  )
+  {
+  }
+}
+
+// Keeps running totals of every metric in memory, which is mostly
+// useful for tests and for printing a summary.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics
+{
+  snapshot: Mutex<MetricsSnapshot>,
+}
+
+// The acquire and wait counts are recorded by acquirers, while the
+// rest are recorded by the shared session. The same InMemoryMetrics
+// can be attached to both sides without counting anything twice.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot
+{
+  pub acquires: u64,
+  pub acquires_refused: u64,
+  pub total_wait: Duration,
+  pub max_wait: Duration,
+  pub queue_depth: usize,
+  pub max_queue_depth: usize,
+  pub refused: u64,
+  pub locks: u64,
+  pub active: usize,
+  pub max_active: usize,
+  pub total_held: Duration,
+  pub max_held: Duration,
+}
+
+impl InMemoryMetrics
+{
+  pub fn new() -> Arc<Self>
+  {
+    Arc::new(InMemoryMetrics::default())
+  }
+
+  pub fn snapshot(&self) -> MetricsSnapshot
+  {
+    self.snapshot.lock().unwrap().clone()
+  }
+}
+
+impl SharedMetrics for InMemoryMetrics
+{
+  fn acquire_completed(
+    &self,
+    wait: Duration,
+    granted: bool,
+  )
+  {
+    let mut snapshot = self.snapshot.lock().unwrap();
+
+    if granted {
+      snapshot.acquires += 1;
+    } else {
+      snapshot.acquires_refused += 1;
+    }
+
+    snapshot.total_wait += wait;
+    snapshot.max_wait = snapshot.max_wait.max(wait);
+  }
+
+  fn queue_depth(
+    &self,
+    depth: usize,
+  )
+  {
+    let mut snapshot = self.snapshot.lock().unwrap();
+
+    snapshot.queue_depth = depth;
+    snapshot.max_queue_depth = snapshot.max_queue_depth.max(depth);
+  }
+
+  fn acquire_refused(&self)
+  {
+    self.snapshot.lock().unwrap().refused += 1;
+  }
+
+  fn lock_acquired(
+    &self,
+    active: usize,
+  )
+  {
+    let mut snapshot = self.snapshot.lock().unwrap();
+
+    snapshot.locks += 1;
+    snapshot.active = active;
+    snapshot.max_active = snapshot.max_active.max(active);
+  }
+
+  fn lock_released(
+    &self,
+    held: Duration,
+    active: usize,
+  )
+  {
+    let mut snapshot = self.snapshot.lock().unwrap();
+
+    snapshot.active = active;
+    snapshot.total_held += held;
+    snapshot.max_held = snapshot.max_held.max(held);
+  }
+}
@@ -1,20 +1,67 @@
 mod channel;
 mod context;
+mod describe;
+mod error;
+mod format;
+mod metrics;
+mod monitor;
 mod protocol;
 mod rec;
+mod scope;
 mod session;
 mod shared;
+#[cfg(feature = "tokio-runtime")]
+mod socket;
+mod trace;
+mod transcript;
+mod transport;
 
 pub mod public;
+pub mod runtime;
 
+#[cfg(feature = "cbor")]
+#[doc(inline)]
+pub use self::format::Cbor;
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use self::format::Json;
+#[cfg(feature = "msgpack")]
+#[doc(inline)]
+pub use self::format::MessagePack;
+#[cfg(feature = "local-executor")]
+#[doc(inline)]
+pub use self::runtime::LocalExecutor;
+#[cfg(feature = "simulation")]
+#[doc(inline)]
+pub use self::runtime::{
+  explore_seeds,
+  Simulation,
+};
+#[cfg(feature = "tokio-runtime")]
+#[doc(inline)]
+pub use self::socket::{
+  connect_shared_channel,
+  connect_shared_channel_with,
+  listen_shared_channel,
+  serve_shared_channel,
+  SocketListener,
+  SocketTransport,
+};
 #[doc(inline)]
 pub use self::{
   channel::{
+    bounded,
     ipc_channel,
     once_channel,
     opaque_channel,
+    run_forward,
     unbounded,
+    BoundedReceiver,
+    BoundedSender,
     ForwardChannel,
+    ForwardFuture,
+    ForwardStep,
+    ForwardSum,
     IpcReceiver,
     IpcSender,
     OpaqueReceiver,
@@ -35,7 +82,34 @@ pub use self::{
     EmptyContext,
     Slot,
   },
+  describe::{
+    describe,
+    describe_row,
+    type_label,
+    Compatibility,
+    Describe,
+    DescribeContext,
+    DescribeRow,
+    DescribeValue,
+    ProtocolDesc,
+  },
+  error::{
+    ChannelError,
+    ForwardError,
+    SessionError,
+  },
+  format::{
+    Bincode,
+    WireFormat,
+  },
+  metrics::{
+    InMemoryMetrics,
+    MetricsSnapshot,
+    SharedMetrics,
+  },
+  monitor::MonitoredTransport,
   protocol::{
+    PositiveProtocol,
     Protocol,
     SharedProtocol,
   },
@@ -51,18 +125,54 @@ pub use self::{
     SharedRecApp,
     SharedRecRow,
   },
+  scope::{
+    spawn_child,
+    ChildTask,
+  },
   session::{
+    unsafe_continue_session,
     unsafe_create_session,
+    unsafe_create_step_session,
     unsafe_run_session,
     PartialSession,
     Session,
+    SessionStep,
   },
   shared::{
+    deserialize_shared_channel,
+    deserialize_shared_channel_with,
+    serialize_shared_channel,
     unsafe_create_shared_channel,
     unsafe_create_shared_session,
     unsafe_receive_shared_channel,
     unsafe_run_shared_session,
+    AcquireMode,
+    AcquireOptions,
+    AcquireReceiver,
+    AcquireRequest,
+    SerializedSharedChannel,
     SharedChannel,
     SharedSession,
   },
+  trace::{
+    trace_action,
+    trace_shared,
+    StepGuard,
+    StepSpan,
+  },
+  transcript::{
+    record_session,
+    replay_transcript,
+    Party,
+    Recorder,
+    Transcript,
+    TranscriptEntry,
+    TranscriptTransport,
+  },
+  transport::{
+    Frame,
+    IpcTransport,
+    Transport,
+    TransportFuture,
+  },
 };
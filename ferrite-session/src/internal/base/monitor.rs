@@ -0,0 +1,608 @@
+use std::{
+  any::Any,
+  collections::VecDeque,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+
+use serde::{
+  self,
+  Deserialize,
+  Serialize,
+};
+
+use super::{
+  describe::{
+    describe,
+    Describe,
+    ProtocolDesc,
+  },
+  error::ChannelError,
+  transcript::Party,
+  transport::{
+    Transport,
+    TransportFuture,
+  },
+};
+
+/*
+   A transport that checks every message going through it against the
+   protocol it is meant to carry. The messages each protocol is made of
+   are the ones sent by ForwardChannel, with the party sending them:
+
+     End                   end(P)
+     SendValue<T, A>       value(P) ack(P) A
+     ReceiveValue<T, A>    ack(C) value(C) ack(P) A
+     SendChannel<A, B>     channel(P) ack(P) B, with ack(P) A on the channel
+     ReceiveChannel<A, B>  ack(C) channel(C) ack(P) B, with ack(C) A on the
+                           channel
+     ExternalChoice<..>    ack(C) value(C) ack(P) choice(P) ack(P) Ai
+     InternalChoice<..>    choice(P) ack(P) Ai
+     LinearToShared<F>     ack(P) F, started over on Release
+     Release               ack(C)
+
+   A message that the protocol does not allow for fails with the step
+   that was expected and the message that was received instead. Steps
+   are counted from 1 on each channel, and the steps of a passed channel
+   are numbered after the step that passed it, as in 4.1, 4.2.
+
+   Nothing is added to the messages on the wire, so the other end does
+   not need to be monitored.
+*/
+
+pub struct MonitoredTransport<Tr>
+{
+  transport: Tr,
+  // A transport received from the other end is only given a monitor
+  // once it is known which protocol it carries. Without one, messages
+  // are passed through unchecked.
+  monitor: Option<Arc<Mutex<Monitor>>>,
+}
+
+#[derive(Clone)]
+struct Monitor
+{
+  local: Party,
+  prefix: String,
+  count: usize,
+  pending: VecDeque<Expected>,
+  rest: Option<Cont>,
+}
+
+#[derive(Clone)]
+struct Expected
+{
+  from: Party,
+  step: &'static str,
+  what: String,
+  kind: ExpectedKind,
+}
+
+#[derive(Clone)]
+enum ExpectedKind
+{
+  Message,
+  Channel(Box<Monitor>),
+  Choice(Vec<Cont>),
+}
+
+// The rest of a protocol, with the enclosing recursive protocols,
+// wrappers and shared protocol that it may refer back to.
+#[derive(Clone)]
+struct Cont
+{
+  desc: ProtocolDesc,
+  recs: Vec<ProtocolDesc>,
+  wrappers: Vec<(String, Cont)>,
+  shared: Option<Box<Cont>>,
+}
+
+impl<Tr> MonitoredTransport<Tr>
+where
+  Tr: Transport,
+{
+  // Monitor a transport that protocol A is forwarded through, with the
+  // local end being the given party of A.
+  pub fn new<A>(
+    transport: Tr,
+    local: Party,
+  ) -> Self
+  where
+    A: Describe,
+  {
+    MonitoredTransport::with_monitor(
+      transport,
+      Monitor::new(local, String::new(), Cont::new(describe::<A>())),
+    )
+  }
+
+  pub fn unmonitored(transport: Tr) -> Self
+  {
+    MonitoredTransport {
+      transport,
+      monitor: None,
+    }
+  }
+
+  pub fn into_inner(self) -> Tr
+  {
+    self.transport
+  }
+
+  fn with_monitor(
+    transport: Tr,
+    monitor: Monitor,
+  ) -> Self
+  {
+    MonitoredTransport {
+      transport,
+      monitor: Some(Arc::new(Mutex::new(monitor))),
+    }
+  }
+}
+
+impl Cont
+{
+  fn new(desc: ProtocolDesc) -> Cont
+  {
+    Cont {
+      desc,
+      recs: Vec::new(),
+      wrappers: Vec::new(),
+      shared: None,
+    }
+  }
+
+  fn with_desc(
+    &self,
+    desc: &ProtocolDesc,
+  ) -> Cont
+  {
+    Cont {
+      desc: desc.clone(),
+      ..self.clone()
+    }
+  }
+
+  // Unfold recursive protocols and wrappers until the protocol starts
+  // with a message. The number of rounds is bounded, as a recursive
+  // protocol can refer to itself without sending anything.
+  fn unfold(mut self) -> Result<Cont, String>
+  {
+    for _ in 0..1024 {
+      match self.desc.clone() {
+        ProtocolDesc::Rec(body) => {
+          self.recs.push(self.desc.clone());
+          self.desc = *body;
+        }
+        ProtocolDesc::Var(i) => {
+          let depth = self
+            .recs
+            .len()
+            .checked_sub(i + 1)
+            .ok_or_else(|| format!("unbound recursion variable {}", i))?;
+
+          self.desc = self.recs[depth].clone();
+          self.recs.truncate(depth);
+        }
+        ProtocolDesc::Wrap(name, Some(body)) => {
+          let wrapper = self.clone();
+
+          self.wrappers.push((name, wrapper));
+          self.desc = *body;
+        }
+        ProtocolDesc::Wrap(name, None) => {
+          let index = self
+            .wrappers
+            .iter()
+            .rposition(|(name2, _)| *name2 == name)
+            .ok_or_else(|| format!("unknown wrapper {}", name))?;
+
+          let (_, wrapper) = self.wrappers[index].clone();
+
+          self = wrapper;
+        }
+        _ => return Ok(self),
+      }
+    }
+
+    Err(format!("protocol {} never sends anything", self.desc))
+  }
+}
+
+impl Monitor
+{
+  fn new(
+    local: Party,
+    prefix: String,
+    cont: Cont,
+  ) -> Monitor
+  {
+    Monitor {
+      local,
+      prefix,
+      count: 0,
+      pending: VecDeque::new(),
+      rest: Some(cont),
+    }
+  }
+
+  fn step_label(&self) -> String
+  {
+    format!("{}{}", self.prefix, self.count)
+  }
+
+  // Turn the rest of the protocol into the messages it starts with.
+  fn expand(&mut self) -> Result<(), ChannelError>
+  {
+    use Party::*;
+
+    let cont = match self.rest.take() {
+      Some(cont) => cont.unfold().map_err(ChannelError::ProtocolDesync)?,
+      None => return Ok(()),
+    };
+
+    // The steps of a passed channel are numbered after the step that
+    // passes it, which is the given number of messages from now.
+    let channel = |from: Party, local: Party, offset: usize, a| {
+      let step = self.count + self.pending.len() + offset;
+
+      let mut child = Monitor::new(
+        local,
+        format!("{}{}.", self.prefix, step),
+        cont.with_desc(a),
+      );
+
+      child.pending.push_back(ack(Provider));
+
+      Expected {
+        from,
+        step: "channel",
+        what: format!("channel from {}", from),
+        kind: ExpectedKind::Channel(Box::new(child)),
+      }
+    };
+
+    let choice = |branches: &[ProtocolDesc]| Expected {
+      from: Provider,
+      step: "choice",
+      what: format!(
+        "choice of one of {} branches from {}",
+        branches.len(),
+        Provider
+      ),
+      kind: ExpectedKind::Choice(
+        branches
+          .iter()
+          .map(|branch| cont.with_desc(branch))
+          .collect(),
+      ),
+    };
+
+    let (messages, rest) = match &cont.desc {
+      ProtocolDesc::End => (vec![message(Provider, "end", "end")], None),
+      ProtocolDesc::SendValue(t, a) => (
+        vec![
+          message(Provider, "value", &format!("value of type {}", t)),
+          ack(Provider),
+        ],
+        Some(cont.with_desc(a)),
+      ),
+      ProtocolDesc::ReceiveValue(t, a) => (
+        vec![
+          ack(Client),
+          message(Client, "value", &format!("value of type {}", t)),
+          ack(Provider),
+        ],
+        Some(cont.with_desc(a)),
+      ),
+      ProtocolDesc::SendChannel(a, b) => (
+        vec![channel(Provider, self.local, 1, a), ack(Provider)],
+        Some(cont.with_desc(b)),
+      ),
+      ProtocolDesc::ReceiveChannel(a, b) => (
+        vec![
+          ack(Client),
+          channel(Client, self.local.other(), 2, a),
+          ack(Provider),
+        ],
+        Some(cont.with_desc(b)),
+      ),
+      ProtocolDesc::ExternalChoice(branches) => (
+        vec![
+          ack(Client),
+          message(Client, "value", "choice selection"),
+          ack(Provider),
+          choice(branches),
+        ],
+        None,
+      ),
+      ProtocolDesc::InternalChoice(branches) => (vec![choice(branches)], None),
+      ProtocolDesc::LinearToShared(f) => {
+        let mut body = cont.with_desc(f);
+
+        body.shared = Some(Box::new(cont.clone()));
+
+        (vec![ack(Provider)], Some(body))
+      }
+      ProtocolDesc::Release => match &cont.shared {
+        Some(shared) => (vec![ack(Client)], Some((**shared).clone())),
+        None => {
+          return Err(ChannelError::ProtocolDesync(
+            "release outside of a shared protocol".to_string(),
+          ))
+        }
+      },
+      desc => {
+        return Err(ChannelError::ProtocolDesync(format!(
+          "protocol {} is not unfolded",
+          desc
+        )))
+      }
+    };
+
+    self.pending.extend(messages);
+    self.rest = rest;
+
+    Ok(())
+  }
+
+  fn peek(&mut self) -> Result<Option<&Expected>, ChannelError>
+  {
+    if self.pending.is_empty() {
+      self.expand()?;
+    }
+
+    Ok(self.pending.front())
+  }
+
+  // Take the next message, which has to be the given step from the
+  // given party.
+  fn next(
+    &mut self,
+    from: Party,
+    step: &str,
+  ) -> Result<Expected, ChannelError>
+  {
+    self.peek()?;
+
+    self.count += 1;
+
+    let found = format!("{} step from {}", step, from);
+
+    match self.pending.pop_front() {
+      Some(expected) if expected.from == from && expected.step == step => {
+        Ok(expected)
+      }
+      Some(expected) => Err(desync(&expected.what, &self.step_label(), found)),
+      None => Err(desync("no more messages", &self.step_label(), found)),
+    }
+  }
+
+  // Continue with the branch picked by a choice.
+  fn select(
+    &mut self,
+    expected: &Expected,
+    index: Option<u64>,
+  ) -> Result<(), ChannelError>
+  {
+    if let ExpectedKind::Choice(branches) = &expected.kind {
+      match index.and_then(|index| branches.get(index as usize)) {
+        Some(branch) => {
+          self.pending.push_back(ack(Party::Provider));
+          self.rest = Some(branch.clone());
+        }
+        None => {
+          let found = match index {
+            Some(index) => format!("branch {}", index),
+            None => "a choice that is not a branch index".to_string(),
+          };
+
+          return Err(desync(&expected.what, &self.step_label(), found));
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn message(
+  from: Party,
+  step: &'static str,
+  what: &str,
+) -> Expected
+{
+  Expected {
+    from,
+    step,
+    what: format!("{} from {}", what, from),
+    kind: ExpectedKind::Message,
+  }
+}
+
+fn ack(from: Party) -> Expected
+{
+  message(from, "ack", "ack")
+}
+
+fn desync(
+  what: &str,
+  label: &str,
+  found: String,
+) -> ChannelError
+{
+  ChannelError::ProtocolDesync(format!(
+    "expected {} at step {}, got {}",
+    what, label, found
+  ))
+}
+
+impl<Tr> Clone for MonitoredTransport<Tr>
+where
+  Tr: Clone,
+{
+  fn clone(&self) -> Self
+  {
+    MonitoredTransport {
+      transport: self.transport.clone(),
+      monitor: self.monitor.clone(),
+    }
+  }
+}
+
+impl<Tr> Transport for MonitoredTransport<Tr>
+where
+  Tr: Transport,
+{
+  // The first end is kept to forward the passed channel through, and
+  // gets the monitor for it. A channel that the protocol does not allow
+  // for is caught once it is sent.
+  fn pair(&self) -> Result<(Self, Self), ChannelError>
+  {
+    let (transport1, transport2) = self.transport.pair()?;
+
+    let child = match &self.monitor {
+      Some(monitor) => {
+        let mut monitor = monitor.lock().unwrap();
+
+        let local = monitor.local;
+
+        match monitor.peek()? {
+          Some(Expected {
+            from,
+            kind: ExpectedKind::Channel(child),
+            ..
+          }) if *from == local => Some((**child).clone()),
+          _ => None,
+        }
+      }
+      None => None,
+    };
+
+    let transport1 = match child {
+      Some(child) => MonitoredTransport::with_monitor(transport1, child),
+      None => MonitoredTransport::unmonitored(transport1),
+    };
+
+    Ok((transport1, MonitoredTransport::unmonitored(transport2)))
+  }
+
+  fn send<T>(
+    &self,
+    step: &'static str,
+    val: T,
+  ) -> TransportFuture<()>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    if let Some(monitor) = &self.monitor {
+      let mut monitor = monitor.lock().unwrap();
+
+      let local = monitor.local;
+
+      let res = monitor.next(local, step).and_then(|expected| {
+        let index = (&val as &dyn Any).downcast_ref::<u64>().copied();
+
+        monitor.select(&expected, index)
+      });
+
+      if let Err(err) = res {
+        return Box::pin(async move { Err(err) });
+      }
+    }
+
+    self.transport.send(step, val)
+  }
+
+  fn recv<T>(
+    &self,
+    step: &'static str,
+  ) -> TransportFuture<T>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let monitor = match &self.monitor {
+      Some(monitor) => monitor.clone(),
+      None => return self.transport.recv(step),
+    };
+
+    let transport = self.transport.clone();
+
+    Box::pin(async move {
+      let (expected, label) = {
+        let mut monitor = monitor.lock().unwrap();
+
+        let remote = monitor.local.other();
+
+        (monitor.next(remote, step)?, monitor.step_label())
+      };
+
+      let mut val =
+        transport.recv::<T>(step).await.map_err(|err| match err {
+          ChannelError::UnexpectedStep { found, .. } => {
+            desync(&expected.what, &label, format!("{} step", found))
+          }
+          ChannelError::Serialization(err) => desync(
+            &expected.what,
+            &label,
+            format!("{} step that does not decode: {}", step, err),
+          ),
+          err => err,
+        })?;
+
+      match &expected.kind {
+        ExpectedKind::Message => {}
+        ExpectedKind::Choice(_) => {
+          let index = (&val as &dyn Any).downcast_ref::<u64>().copied();
+
+          monitor.lock().unwrap().select(&expected, index)?;
+        }
+        ExpectedKind::Channel(child) => {
+          if let Some(channel) =
+            (&mut val as &mut dyn Any).downcast_mut::<MonitoredTransport<Tr>>()
+          {
+            channel.monitor = Some(Arc::new(Mutex::new((**child).clone())));
+          }
+        }
+      }
+
+      Ok(val)
+    })
+  }
+}
+
+// Only the underlying transport goes over the wire.
+impl<Tr> serde::Serialize for MonitoredTransport<Tr>
+where
+  Tr: Serialize,
+{
+  fn serialize<S>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    self.transport.serialize(serializer)
+  }
+}
+
+impl<'a, Tr> serde::Deserialize<'a> for MonitoredTransport<Tr>
+where
+  Tr: Deserialize<'a>,
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'a>,
+  {
+    Ok(MonitoredTransport {
+      transport: Tr::deserialize(deserializer)?,
+      monitor: None,
+    })
+  }
+}
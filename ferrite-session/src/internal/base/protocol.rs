@@ -8,6 +8,13 @@ pub trait SharedProtocol: Send + 'static
 {
 }
 
+// A protocol whose provider takes the first step, such as sending a
+// value or picking a branch, so that offering it is the same as taking
+// that step. These are the positive protocols of the typing rules.
+pub trait PositiveProtocol: Protocol
+{
+}
+
 impl Protocol for Z {}
 
 impl<N> Protocol for S<N> where N: Protocol {}
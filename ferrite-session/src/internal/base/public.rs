@@ -1,13 +1,71 @@
+#[cfg(feature = "cbor")]
+#[doc(inline)]
+pub use super::Cbor;
+#[cfg(feature = "json")]
+#[doc(inline)]
+pub use super::Json;
+#[cfg(feature = "local-executor")]
+#[doc(inline)]
+pub use super::LocalExecutor;
+#[cfg(feature = "msgpack")]
+#[doc(inline)]
+pub use super::MessagePack;
+#[cfg(feature = "tokio-runtime")]
+#[doc(inline)]
+pub use super::{
+  connect_shared_channel,
+  connect_shared_channel_with,
+  listen_shared_channel,
+  serve_shared_channel,
+  SocketListener,
+  SocketTransport,
+};
 #[doc(inline)]
 pub use super::{
+  describe,
+  deserialize_shared_channel,
+  deserialize_shared_channel_with,
+  record_session,
+  replay_transcript,
+  serialize_shared_channel,
+  AcquireOptions,
+  Bincode,
+  ChannelError,
+  Compatibility,
+  Describe,
+  DescribeValue,
   Empty,
+  ForwardError,
+  Frame,
+  InMemoryMetrics,
+  IpcTransport,
+  MetricsSnapshot,
+  MonitoredTransport,
   PartialSession,
+  Party,
+  ProtocolDesc,
   Rec,
   RecX,
+  Recorder,
   Release,
+  SerializedSharedChannel,
   Session,
+  SessionError,
   SharedChannel,
+  SharedMetrics,
   SharedSession,
+  Transcript,
+  TranscriptEntry,
+  TranscriptTransport,
+  Transport,
+  TransportFuture,
+  WireFormat,
+};
+#[cfg(feature = "simulation")]
+#[doc(inline)]
+pub use super::{
+  explore_seeds,
+  Simulation,
 };
 
 pub trait Protocol: super::Protocol
@@ -22,6 +80,12 @@ pub trait SharedProtocol: super::SharedProtocol
 
 impl<A> SharedProtocol for A where A: super::SharedProtocol {}
 
+pub trait PositiveProtocol: super::PositiveProtocol
+{
+}
+
+impl<A> PositiveProtocol for A where A: super::PositiveProtocol {}
+
 pub trait Context: super::Context
 {
 }
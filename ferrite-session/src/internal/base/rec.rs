@@ -1,6 +1,24 @@
 use std::marker::PhantomData;
 
-use super::protocol::Protocol;
+use super::{
+  channel::{
+    ForwardChannel,
+    ForwardFuture,
+    ForwardStep,
+  },
+  describe::{
+    describe_row,
+    Describe,
+    DescribeContext,
+    DescribeRow,
+    ProtocolDesc,
+  },
+  protocol::{
+    PositiveProtocol,
+    Protocol,
+  },
+  transport::Transport,
+};
 use crate::internal::functional::{
   nat::{
     S,
@@ -75,6 +93,45 @@ where
 {
 }
 
+// A recursive protocol is positive if its unfolding is.
+impl<C, F, T> PositiveProtocol for RecX<C, F>
+where
+  C: Send + 'static,
+  F: Send + 'static,
+  F: RecApp<(RecX<C, F>, C), Applied = T>,
+  T: PositiveProtocol,
+{
+}
+
+impl<C, F, T> ForwardChannel for RecX<C, F>
+where
+  C: Send + 'static,
+  F: Send + 'static,
+  F: RecApp<(RecX<C, F>, C), Applied = T>,
+  T: ForwardChannel,
+{
+  fn forward_to<Tr>(
+    self,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    unfix(self).forward_to(transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let (applied, rest) = T::forward_from(transport).await?;
+
+      Ok((fix(applied), rest))
+    })
+  }
+}
+
 impl<C, F> RecApp<C> for RecX<(), F>
 where
   C: Send + 'static,
@@ -195,3 +252,48 @@ where
 {
   type Row = Row3;
 }
+
+// The recursion variables beyond the one bound here refer to the
+// protocols in C, which are filled in so that an unfolded recursive
+// protocol is described the same way wherever it occurs.
+impl<C, F> Describe for RecX<C, F>
+where
+  C: DescribeRow,
+  F: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    let context = describe_row::<C>(ctx);
+
+    ProtocolDesc::Rec(Box::new(F::describe_in(ctx).close(1, &context)))
+  }
+}
+
+impl Describe for Release
+{
+  fn describe_in(_: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::Release
+  }
+}
+
+impl Describe for Z
+{
+  fn describe_in(_: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::Var(0)
+  }
+}
+
+impl<N> Describe for S<N>
+where
+  N: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    match N::describe_in(ctx) {
+      ProtocolDesc::Var(i) => ProtocolDesc::Var(i + 1),
+      desc => desc,
+    }
+  }
+}
@@ -0,0 +1,649 @@
+use std::{
+  cell::RefCell,
+  collections::{
+    BTreeMap,
+    VecDeque,
+  },
+  future::Future,
+  panic::{
+    self,
+    AssertUnwindSafe,
+  },
+  pin::Pin,
+  sync::{
+    atomic::{
+      AtomicBool,
+      Ordering,
+    },
+    Arc,
+    Condvar,
+    Mutex,
+    OnceLock,
+  },
+  task::{
+    Context,
+    Poll,
+    Wake,
+    Waker,
+  },
+  thread,
+  time::{
+    Duration,
+    Instant,
+  },
+};
+
+use futures::FutureExt;
+
+use super::JoinError;
+
+#[cfg(feature = "simulation")]
+mod sim;
+
+#[cfg(feature = "simulation")]
+pub use self::sim::{
+  explore_seeds,
+  Simulation,
+};
+
+// A minimal single-threaded executor. All spawned tasks are run on the
+// thread that calls `block_on`, interleaved with the main future.
+// Tasks still need to be `Send`, as they can be woken from other
+// threads, such as the ones running blocking tasks and timers.
+pub struct LocalExecutor
+{
+  queue: Arc<Queue>,
+}
+
+pub struct RawHandle<T>
+{
+  state: Arc<Mutex<JoinState<T>>>,
+  task: Option<Arc<Task>>,
+}
+
+pub enum Sleep
+{
+  Timer(TimerSleep),
+  #[cfg(feature = "simulation")]
+  Simulated(sim::SimSleep),
+}
+
+pub struct TimerSleep
+{
+  deadline: Instant,
+  key: Option<(Instant, u64)>,
+}
+
+struct Queue
+{
+  state: Mutex<QueueState>,
+  ready: Condvar,
+}
+
+struct QueueState
+{
+  tasks: VecDeque<Arc<Task>>,
+  main_woken: bool,
+  #[cfg(feature = "simulation")]
+  sim: Option<sim::SimState>,
+}
+
+struct Task
+{
+  future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+  queued: AtomicBool,
+  aborted: AtomicBool,
+  queue: Arc<Queue>,
+}
+
+struct MainWaker
+{
+  queue: Arc<Queue>,
+}
+
+struct JoinState<T>
+{
+  output: Option<Result<T, JoinError>>,
+  waker: Option<Waker>,
+}
+
+// Completes the join state of a task. If it is dropped before the task
+// produces an output, the task has been aborted.
+struct Completion<T>
+{
+  state: Arc<Mutex<JoinState<T>>>,
+}
+
+struct Timer
+{
+  entries: Mutex<TimerEntries>,
+  changed: Condvar,
+}
+
+struct TimerEntries
+{
+  next_id: u64,
+  wakers: BTreeMap<(Instant, u64), Waker>,
+}
+
+thread_local! {
+  static CURRENT: RefCell<Option<Arc<Queue>>> = const { RefCell::new(None) };
+}
+
+pub fn spawn<Fut>(future: Fut) -> RawHandle<Fut::Output>
+where
+  Fut: Future + Send + 'static,
+  Fut::Output: Send + 'static,
+{
+  let queue = current_queue()
+    .expect("sessions must be spawned from within LocalExecutor::block_on");
+
+  let state = JoinState::new();
+
+  let completion = Completion {
+    state: state.clone(),
+  };
+
+  let task = Arc::new(Task {
+    future: Mutex::new(Some(Box::pin(async move {
+      let res = AssertUnwindSafe(future).catch_unwind().await;
+
+      completion.complete(res.map_err(|_| JoinError { panicked: true }));
+    }))),
+    queued: AtomicBool::new(false),
+    aborted: AtomicBool::new(false),
+    queue,
+  });
+
+  task.schedule();
+
+  RawHandle {
+    state,
+    task: Some(task),
+  }
+}
+
+pub fn spawn_blocking<F, R>(f: F) -> RawHandle<R>
+where
+  F: FnOnce() -> R + Send + 'static,
+  R: Send + 'static,
+{
+  let state = JoinState::new();
+
+  let completion = Completion {
+    state: state.clone(),
+  };
+
+  #[cfg(feature = "simulation")]
+  let guard = sim::BlockingGuard::enter();
+
+  thread::spawn(move || {
+    // Only leave the blocking section after the result is delivered.
+    #[cfg(feature = "simulation")]
+    let _guard = guard;
+
+    let res = panic::catch_unwind(AssertUnwindSafe(f));
+
+    completion.complete(res.map_err(|_| JoinError { panicked: true }));
+  });
+
+  RawHandle { state, task: None }
+}
+
+pub fn sleep(duration: Duration) -> Sleep
+{
+  #[cfg(feature = "simulation")]
+  {
+    if let Some(sleep) = sim::SimSleep::new(duration) {
+      return Sleep::Simulated(sleep);
+    }
+  }
+
+  Sleep::Timer(TimerSleep {
+    deadline: Instant::now() + duration,
+    key: None,
+  })
+}
+
+pub async fn yield_now()
+{
+  #[cfg(feature = "simulation")]
+  {
+    if sim::is_simulated() {
+      sim::YieldNow { yielded: false }.await
+    }
+  }
+}
+
+#[cfg(feature = "tokio-runtime")]
+pub fn is_current() -> bool
+{
+  current_queue().is_some()
+}
+
+fn current_queue() -> Option<Arc<Queue>>
+{
+  CURRENT.with(|current| current.borrow().clone())
+}
+
+impl LocalExecutor
+{
+  pub fn new() -> Self
+  {
+    LocalExecutor {
+      queue: Arc::new(Queue {
+        state: Mutex::new(QueueState {
+          tasks: VecDeque::new(),
+          main_woken: false,
+          #[cfg(feature = "simulation")]
+          sim: None,
+        }),
+        ready: Condvar::new(),
+      }),
+    }
+  }
+
+  // Run the future to completion on the current thread, together with
+  // all the tasks that it spawns. Tasks that are still pending when the
+  // future completes are resumed by the next call to `block_on`.
+  pub fn block_on<Fut>(
+    &self,
+    future: Fut,
+  ) -> Fut::Output
+  where
+    Fut: Future,
+  {
+    let _enter = Enter::new(self.queue.clone());
+
+    let waker = Waker::from(Arc::new(MainWaker {
+      queue: self.queue.clone(),
+    }));
+
+    let mut cx = Context::from_waker(&waker);
+
+    futures::pin_mut!(future);
+
+    loop {
+      if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+        return output;
+      }
+
+      while let Some(task) = self.queue.next() {
+        task.run();
+      }
+    }
+  }
+}
+
+// Makes the executor current for the duration of `block_on`, and
+// restores the previous one even if the future panics.
+struct Enter
+{
+  previous: Option<Arc<Queue>>,
+}
+
+impl Enter
+{
+  fn new(queue: Arc<Queue>) -> Self
+  {
+    Enter {
+      previous: CURRENT.with(|current| current.replace(Some(queue))),
+    }
+  }
+}
+
+impl Drop for Enter
+{
+  fn drop(&mut self)
+  {
+    let previous = self.previous.take();
+
+    CURRENT.with(|current| current.replace(previous));
+  }
+}
+
+impl Default for LocalExecutor
+{
+  fn default() -> Self
+  {
+    Self::new()
+  }
+}
+
+impl Drop for LocalExecutor
+{
+  fn drop(&mut self)
+  {
+    // Queued tasks refer back to the queue, so drop their futures to
+    // break the cycle. Dropping them may in turn schedule other tasks.
+    loop {
+      let tasks: Vec<_> =
+        self.queue.state.lock().unwrap().tasks.drain(..).collect();
+
+      if tasks.is_empty() {
+        break;
+      }
+
+      for task in tasks {
+        let future = task.future.lock().unwrap().take();
+
+        drop(future);
+      }
+    }
+  }
+}
+
+impl Queue
+{
+  // Block until either a task is ready to run, or the main future has
+  // been woken, in which case `None` is returned.
+  fn next(&self) -> Option<Arc<Task>>
+  {
+    let mut state = self.state.lock().unwrap();
+
+    loop {
+      #[cfg(feature = "simulation")]
+      {
+        if state.sim.is_some() {
+          match sim::next(&mut state) {
+            sim::Next::Main => return None,
+            sim::Next::Task(task) => return Some(task),
+            sim::Next::Wake(wakers) => {
+              // Wakers schedule onto this queue, so they must be woken
+              // without holding the lock.
+              drop(state);
+
+              for waker in wakers {
+                waker.wake();
+              }
+
+              state = self.state.lock().unwrap();
+            }
+            sim::Next::Wait => {
+              state = self.ready.wait(state).unwrap();
+            }
+            sim::Next::Deadlock => {
+              drop(state);
+
+              panic!("simulation deadlocked: every task is blocked");
+            }
+          }
+
+          continue;
+        }
+      }
+
+      if state.main_woken {
+        state.main_woken = false;
+
+        return None;
+      }
+
+      if let Some(task) = state.tasks.pop_front() {
+        return Some(task);
+      }
+
+      state = self.ready.wait(state).unwrap();
+    }
+  }
+
+  fn push(
+    &self,
+    task: Arc<Task>,
+  )
+  {
+    self.state.lock().unwrap().tasks.push_back(task);
+    self.ready.notify_one();
+  }
+}
+
+impl Task
+{
+  fn schedule(self: &Arc<Self>)
+  {
+    if !self.queued.swap(true, Ordering::AcqRel) {
+      self.queue.push(self.clone());
+    }
+  }
+
+  fn run(self: Arc<Self>)
+  {
+    self.queued.store(false, Ordering::Release);
+
+    let waker = Waker::from(self.clone());
+
+    let mut cx = Context::from_waker(&waker);
+
+    let mut slot = self.future.lock().unwrap();
+
+    let finished = match slot.as_mut() {
+      Some(future) => {
+        self.aborted.load(Ordering::Acquire)
+          || future.as_mut().poll(&mut cx).is_ready()
+      }
+      None => false,
+    };
+
+    // The finished future is dropped only after the lock is released,
+    // as dropping it may abort or wake other tasks.
+    let future = if finished { slot.take() } else { None };
+
+    drop(slot);
+    drop(future);
+  }
+}
+
+impl Wake for Task
+{
+  fn wake(self: Arc<Self>)
+  {
+    self.schedule()
+  }
+
+  fn wake_by_ref(self: &Arc<Self>)
+  {
+    self.schedule()
+  }
+}
+
+impl Wake for MainWaker
+{
+  fn wake(self: Arc<Self>)
+  {
+    self.wake_by_ref()
+  }
+
+  fn wake_by_ref(self: &Arc<Self>)
+  {
+    self.queue.state.lock().unwrap().main_woken = true;
+    self.queue.ready.notify_one();
+  }
+}
+
+impl<T> JoinState<T>
+{
+  fn new() -> Arc<Mutex<Self>>
+  {
+    Arc::new(Mutex::new(JoinState {
+      output: None,
+      waker: None,
+    }))
+  }
+}
+
+impl<T> Completion<T>
+{
+  fn complete(
+    &self,
+    res: Result<T, JoinError>,
+  )
+  {
+    let mut state = self.state.lock().unwrap();
+
+    if state.output.is_none() {
+      state.output = Some(res);
+
+      if let Some(waker) = state.waker.take() {
+        waker.wake();
+      }
+    }
+  }
+}
+
+impl<T> Drop for Completion<T>
+{
+  fn drop(&mut self)
+  {
+    self.complete(Err(JoinError { panicked: false }));
+  }
+}
+
+impl<T> RawHandle<T>
+{
+  pub fn abort(&self)
+  {
+    if let Some(task) = &self.task {
+      task.aborted.store(true, Ordering::Release);
+      task.schedule();
+    }
+  }
+}
+
+impl<T> Future for RawHandle<T>
+{
+  type Output = Result<T, JoinError>;
+
+  fn poll(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Self::Output>
+  {
+    let mut state = self.state.lock().unwrap();
+
+    match state.output.take() {
+      Some(output) => Poll::Ready(output),
+      None => {
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+      }
+    }
+  }
+}
+
+// All sleeps share a single timer thread, which wakes them up in order
+// of their deadlines.
+fn timer() -> &'static Timer
+{
+  static TIMER: OnceLock<Timer> = OnceLock::new();
+
+  TIMER.get_or_init(|| {
+    thread::spawn(run_timer);
+
+    Timer {
+      entries: Mutex::new(TimerEntries {
+        next_id: 0,
+        wakers: BTreeMap::new(),
+      }),
+      changed: Condvar::new(),
+    }
+  })
+}
+
+fn run_timer()
+{
+  let timer = timer();
+
+  let mut entries = timer.entries.lock().unwrap();
+
+  loop {
+    let now = Instant::now();
+
+    while let Some(&key) = entries.wakers.keys().next() {
+      if key.0 > now {
+        break;
+      }
+
+      if let Some(waker) = entries.wakers.remove(&key) {
+        waker.wake();
+      }
+    }
+
+    entries = match entries.wakers.keys().next() {
+      Some(&(deadline, _)) => {
+        timer
+          .changed
+          .wait_timeout(entries, deadline - now)
+          .unwrap()
+          .0
+      }
+      None => timer.changed.wait(entries).unwrap(),
+    };
+  }
+}
+
+impl Future for Sleep
+{
+  type Output = ();
+
+  fn poll(
+    self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<()>
+  {
+    match self.get_mut() {
+      Sleep::Timer(sleep) => Pin::new(sleep).poll(cx),
+      #[cfg(feature = "simulation")]
+      Sleep::Simulated(sleep) => Pin::new(sleep).poll(cx),
+    }
+  }
+}
+
+impl Future for TimerSleep
+{
+  type Output = ();
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<()>
+  {
+    let timer = timer();
+
+    let mut entries = timer.entries.lock().unwrap();
+
+    if Instant::now() >= self.deadline {
+      if let Some(key) = self.key.take() {
+        entries.wakers.remove(&key);
+      }
+
+      return Poll::Ready(());
+    }
+
+    let key = match self.key {
+      Some(key) => key,
+      None => {
+        let key = (self.deadline, entries.next_id);
+
+        entries.next_id += 1;
+        self.key = Some(key);
+
+        key
+      }
+    };
+
+    entries.wakers.insert(key, cx.waker().clone());
+    timer.changed.notify_one();
+
+    Poll::Pending
+  }
+}
+
+impl Drop for TimerSleep
+{
+  fn drop(&mut self)
+  {
+    if let Some(key) = self.key.take() {
+      timer().entries.lock().unwrap().wakers.remove(&key);
+    }
+  }
+}
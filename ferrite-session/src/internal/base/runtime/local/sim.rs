@@ -0,0 +1,364 @@
+use std::{
+  collections::BTreeMap,
+  future::Future,
+  panic::{
+    self,
+    AssertUnwindSafe,
+  },
+  pin::Pin,
+  sync::{
+    Arc,
+    Condvar,
+    Mutex,
+  },
+  task::{
+    Context,
+    Poll,
+    Waker,
+  },
+  time::Duration,
+};
+
+use super::{
+  current_queue,
+  LocalExecutor,
+  Queue,
+  QueueState,
+  Task,
+};
+
+// Runs a session program under a deterministic scheduler. Whenever
+// more than one task is ready to run, the next one is picked by a
+// random number generator seeded with the given seed, and every receive
+// on a channel gives the scheduler a chance to switch tasks. Sleeps and
+// timeouts use a virtual clock that only advances once every task is
+// blocked, so a program gives the same interleaving for the same seed
+// regardless of how long each step takes in real time.
+//
+// Blocking tasks, such as the ones used by IPC transports, still run on
+// their own threads and are outside of the scheduler's control.
+pub struct Simulation
+{
+  seed: u64,
+}
+
+pub struct SimState
+{
+  rng: u64,
+  now: Duration,
+  next_timer: u64,
+  timers: BTreeMap<(Duration, u64), Waker>,
+  blocking: usize,
+}
+
+pub struct SimSleep
+{
+  queue: Arc<Queue>,
+  deadline: Duration,
+  key: Option<(Duration, u64)>,
+}
+
+pub struct YieldNow
+{
+  pub yielded: bool,
+}
+
+// Keeps the virtual clock from advancing while a blocking task is
+// running, since it may yet wake up one of the simulated tasks.
+pub struct BlockingGuard
+{
+  queue: Option<Arc<Queue>>,
+}
+
+pub enum Next
+{
+  Main,
+  Task(Arc<Task>),
+  Wake(Vec<Waker>),
+  Wait,
+  Deadlock,
+}
+
+// Run the program produced by make once for each seed, and stop at the
+// first seed for which it panics. That seed can then be replayed with
+// `Simulation::new(seed).run(..)`.
+pub fn explore_seeds<I, F, Fut>(
+  seeds: I,
+  mut make: F,
+) -> Result<(), u64>
+where
+  I: IntoIterator<Item = u64>,
+  F: FnMut() -> Fut,
+  Fut: Future,
+{
+  for seed in seeds {
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+      Simulation::new(seed).run(make());
+    }));
+
+    if res.is_err() {
+      error!("[explore_seeds] simulation failed with seed {}", seed);
+
+      return Err(seed);
+    }
+  }
+
+  Ok(())
+}
+
+impl Simulation
+{
+  pub fn new(seed: u64) -> Self
+  {
+    Simulation { seed }
+  }
+
+  pub fn seed(&self) -> u64
+  {
+    self.seed
+  }
+
+  // Run the future to completion on a fresh simulated executor. Panics
+  // if every task is blocked before the future completes, as the
+  // program would otherwise hang.
+  pub fn run<Fut>(
+    &self,
+    future: Fut,
+  ) -> Fut::Output
+  where
+    Fut: Future,
+  {
+    debug!(
+      "[Simulation::run] running simulation with seed {}",
+      self.seed
+    );
+
+    LocalExecutor::simulated(self.seed).block_on(future)
+  }
+}
+
+impl LocalExecutor
+{
+  fn simulated(seed: u64) -> Self
+  {
+    LocalExecutor {
+      queue: Arc::new(Queue {
+        state: Mutex::new(QueueState {
+          tasks: Default::default(),
+          main_woken: false,
+          sim: Some(SimState {
+            rng: seed,
+            now: Duration::from_secs(0),
+            next_timer: 0,
+            timers: BTreeMap::new(),
+            blocking: 0,
+          }),
+        }),
+        ready: Condvar::new(),
+      }),
+    }
+  }
+}
+
+impl SimState
+{
+  // splitmix64, which is good enough for picking tasks and needs no
+  // external dependency.
+  fn next_u64(&mut self) -> u64
+  {
+    self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = self.rng;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  fn pick(
+    &mut self,
+    count: usize,
+  ) -> usize
+  {
+    (self.next_u64() % count as u64) as usize
+  }
+}
+
+// Pick what the executor should do next. The main future takes part in
+// the draw together with the ready tasks. When nothing is ready, the
+// virtual clock jumps to the earliest pending sleep.
+pub fn next(state: &mut QueueState) -> Next
+{
+  let QueueState {
+    tasks,
+    main_woken,
+    sim,
+  } = state;
+
+  let sim = sim.as_mut().unwrap();
+
+  let count = tasks.len() + usize::from(*main_woken);
+
+  if count > 0 {
+    let index = sim.pick(count);
+
+    if index == tasks.len() {
+      *main_woken = false;
+
+      return Next::Main;
+    }
+
+    return Next::Task(tasks.remove(index).unwrap());
+  }
+
+  if sim.blocking > 0 {
+    return Next::Wait;
+  }
+
+  let deadline = match sim.timers.keys().next() {
+    Some(&(deadline, _)) => deadline,
+    None => return Next::Deadlock,
+  };
+
+  sim.now = deadline;
+
+  let mut wakers = Vec::new();
+
+  while let Some(&key) = sim.timers.keys().next() {
+    if key.0 > deadline {
+      break;
+    }
+
+    wakers.extend(sim.timers.remove(&key));
+  }
+
+  Next::Wake(wakers)
+}
+
+pub fn is_simulated() -> bool
+{
+  match current_queue() {
+    Some(queue) => queue.state.lock().unwrap().sim.is_some(),
+    None => false,
+  }
+}
+
+impl SimSleep
+{
+  pub fn new(duration: Duration) -> Option<Self>
+  {
+    let queue = current_queue()?;
+
+    let now = queue.state.lock().unwrap().sim.as_ref()?.now;
+
+    Some(SimSleep {
+      queue,
+      deadline: now + duration,
+      key: None,
+    })
+  }
+}
+
+impl Future for SimSleep
+{
+  type Output = ();
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<()>
+  {
+    let queue = self.queue.clone();
+
+    let mut state = queue.state.lock().unwrap();
+
+    let sim = state.sim.as_mut().unwrap();
+
+    if sim.now >= self.deadline {
+      if let Some(key) = self.key.take() {
+        sim.timers.remove(&key);
+      }
+
+      return Poll::Ready(());
+    }
+
+    let key = match self.key {
+      Some(key) => key,
+      None => {
+        let key = (self.deadline, sim.next_timer);
+
+        sim.next_timer += 1;
+        self.key = Some(key);
+
+        key
+      }
+    };
+
+    sim.timers.insert(key, cx.waker().clone());
+
+    Poll::Pending
+  }
+}
+
+impl Drop for SimSleep
+{
+  fn drop(&mut self)
+  {
+    if let Some(key) = self.key.take() {
+      if let Some(sim) = &mut self.queue.state.lock().unwrap().sim {
+        sim.timers.remove(&key);
+      }
+    }
+  }
+}
+
+impl Future for YieldNow
+{
+  type Output = ();
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<()>
+  {
+    if self.yielded {
+      Poll::Ready(())
+    } else {
+      self.yielded = true;
+      cx.waker().wake_by_ref();
+
+      Poll::Pending
+    }
+  }
+}
+
+impl BlockingGuard
+{
+  pub fn enter() -> Self
+  {
+    let queue = current_queue().filter(|queue| {
+      match &mut queue.state.lock().unwrap().sim {
+        Some(sim) => {
+          sim.blocking += 1;
+          true
+        }
+        None => false,
+      }
+    });
+
+    BlockingGuard { queue }
+  }
+}
+
+impl Drop for BlockingGuard
+{
+  fn drop(&mut self)
+  {
+    if let Some(queue) = self.queue.take() {
+      if let Some(sim) = &mut queue.state.lock().unwrap().sim {
+        sim.blocking -= 1;
+      }
+
+      queue.ready.notify_one();
+    }
+  }
+}
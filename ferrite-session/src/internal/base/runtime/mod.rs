@@ -0,0 +1,255 @@
+// The runtime that Ferrite spawns its session tasks on. Session code
+// never talks to an executor directly, and goes through the functions
+// here instead. The `tokio-runtime` feature (on by default) spawns onto
+// the ambient tokio runtime, which also works inside a tokio `LocalSet`.
+// The `local-executor` feature adds the built-in single-threaded
+// `LocalExecutor`, which is only used by code running inside its
+// `block_on`, so that enabling it never takes sessions off tokio
+// elsewhere. Sockets still need tokio, and cannot be used from within
+// the local executor. The `simulation` feature additionally provides a
+// seeded deterministic variant of the local executor for testing.
+
+use std::{
+  error::Error,
+  fmt,
+  future::Future,
+  pin::Pin,
+  task::{
+    Context,
+    Poll,
+  },
+  time::Duration,
+};
+
+use futures::future::{
+  self,
+  Either,
+};
+
+#[cfg(feature = "local-executor")]
+mod local;
+#[cfg(feature = "tokio-runtime")]
+mod tokio_rt;
+
+#[cfg(feature = "local-executor")]
+pub use self::local::LocalExecutor;
+#[cfg(feature = "simulation")]
+pub use self::local::{
+  explore_seeds,
+  Simulation,
+};
+
+#[cfg(not(any(feature = "tokio-runtime", feature = "local-executor")))]
+compile_error!(
+  "ferrite-session requires either the `tokio-runtime` or the \
+   `local-executor` feature"
+);
+
+pub struct JoinHandle<T>
+{
+  raw: RawHandle<T>,
+}
+
+enum RawHandle<T>
+{
+  #[cfg(feature = "tokio-runtime")]
+  Tokio(tokio_rt::RawHandle<T>),
+  #[cfg(feature = "local-executor")]
+  Local(local::RawHandle<T>),
+}
+
+#[derive(Clone, Copy)]
+enum Executor
+{
+  #[cfg(feature = "tokio-runtime")]
+  Tokio,
+  #[cfg(feature = "local-executor")]
+  Local,
+}
+
+#[derive(Debug)]
+pub struct JoinError
+{
+  panicked: bool,
+}
+
+#[derive(Debug)]
+pub struct Elapsed;
+
+// The local executor is only picked from within its `block_on`, or
+// when it is the only one available.
+fn current_executor() -> Executor
+{
+  #[cfg(all(feature = "tokio-runtime", feature = "local-executor"))]
+  {
+    if local::is_current() {
+      Executor::Local
+    } else {
+      Executor::Tokio
+    }
+  }
+
+  #[cfg(all(feature = "tokio-runtime", not(feature = "local-executor")))]
+  {
+    Executor::Tokio
+  }
+
+  #[cfg(all(feature = "local-executor", not(feature = "tokio-runtime")))]
+  {
+    Executor::Local
+  }
+}
+
+pub fn spawn<Fut>(future: Fut) -> JoinHandle<Fut::Output>
+where
+  Fut: Future + Send + 'static,
+  Fut::Output: Send + 'static,
+{
+  let future = super::trace::in_current_span(future);
+
+  let raw = match current_executor() {
+    #[cfg(feature = "tokio-runtime")]
+    Executor::Tokio => RawHandle::Tokio(tokio_rt::spawn(future)),
+    #[cfg(feature = "local-executor")]
+    Executor::Local => RawHandle::Local(local::spawn(future)),
+  };
+
+  JoinHandle { raw }
+}
+
+pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+  F: FnOnce() -> R + Send + 'static,
+  R: Send + 'static,
+{
+  let raw = match current_executor() {
+    #[cfg(feature = "tokio-runtime")]
+    Executor::Tokio => RawHandle::Tokio(tokio_rt::spawn_blocking(f)),
+    #[cfg(feature = "local-executor")]
+    Executor::Local => RawHandle::Local(local::spawn_blocking(f)),
+  };
+
+  JoinHandle { raw }
+}
+
+pub async fn sleep(duration: Duration)
+{
+  raw_sleep(duration).await
+}
+
+fn raw_sleep(duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send>>
+{
+  match current_executor() {
+    #[cfg(feature = "tokio-runtime")]
+    Executor::Tokio => Box::pin(tokio_rt::sleep(duration)),
+    #[cfg(feature = "local-executor")]
+    Executor::Local => Box::pin(local::sleep(duration)),
+  }
+}
+
+// A point at which the scheduler may switch to another task. Only the
+// simulation scheduler makes use of it, and it does nothing otherwise.
+pub async fn yield_now()
+{
+  match current_executor() {
+    #[cfg(feature = "tokio-runtime")]
+    Executor::Tokio => tokio_rt::yield_now().await,
+    #[cfg(feature = "local-executor")]
+    Executor::Local => local::yield_now().await,
+  }
+}
+
+pub async fn timeout<Fut>(
+  duration: Duration,
+  future: Fut,
+) -> Result<Fut::Output, Elapsed>
+where
+  Fut: Future,
+{
+  let sleep = raw_sleep(duration);
+
+  futures::pin_mut!(future, sleep);
+
+  // The future is polled first, so that a value that is already
+  // available is never lost to the deadline.
+  match future::select(future, sleep).await {
+    Either::Left((output, _)) => Ok(output),
+    Either::Right(_) => Err(Elapsed),
+  }
+}
+
+impl<T> JoinHandle<T>
+{
+  // Cancel the task, dropping its future the next time the executor
+  // gets to it. Has no effect on blocking tasks.
+  pub fn abort(&self)
+  {
+    match &self.raw {
+      #[cfg(feature = "tokio-runtime")]
+      RawHandle::Tokio(raw) => raw.abort(),
+      #[cfg(feature = "local-executor")]
+      RawHandle::Local(raw) => raw.abort(),
+    }
+  }
+}
+
+impl<T> Future for JoinHandle<T>
+{
+  type Output = Result<T, JoinError>;
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Self::Output>
+  {
+    match &mut self.raw {
+      #[cfg(feature = "tokio-runtime")]
+      RawHandle::Tokio(raw) => Pin::new(raw).poll(cx),
+      #[cfg(feature = "local-executor")]
+      RawHandle::Local(raw) => Pin::new(raw).poll(cx),
+    }
+  }
+}
+
+impl JoinError
+{
+  pub fn is_panic(&self) -> bool
+  {
+    self.panicked
+  }
+
+  pub fn is_cancelled(&self) -> bool
+  {
+    !self.panicked
+  }
+}
+
+impl fmt::Display for JoinError
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    if self.panicked {
+      write!(f, "task panicked")
+    } else {
+      write!(f, "task was cancelled")
+    }
+  }
+}
+
+impl Error for JoinError {}
+
+impl fmt::Display for Elapsed
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    write!(f, "deadline has elapsed")
+  }
+}
+
+impl Error for Elapsed {}
@@ -0,0 +1,64 @@
+use std::{
+  future::Future,
+  pin::Pin,
+  task::{
+    Context,
+    Poll,
+  },
+  time::Duration,
+};
+
+use tokio::{
+  task,
+  time,
+};
+
+use super::JoinError;
+
+pub struct RawHandle<T>(task::JoinHandle<T>);
+
+pub fn spawn<Fut>(future: Fut) -> RawHandle<Fut::Output>
+where
+  Fut: Future + Send + 'static,
+  Fut::Output: Send + 'static,
+{
+  RawHandle(task::spawn(future))
+}
+
+pub fn spawn_blocking<F, R>(f: F) -> RawHandle<R>
+where
+  F: FnOnce() -> R + Send + 'static,
+  R: Send + 'static,
+{
+  RawHandle(task::spawn_blocking(f))
+}
+
+pub fn sleep(duration: Duration) -> time::Sleep
+{
+  time::sleep(duration)
+}
+
+pub async fn yield_now() {}
+
+impl<T> RawHandle<T>
+{
+  pub fn abort(&self)
+  {
+    self.0.abort()
+  }
+}
+
+impl<T> Future for RawHandle<T>
+{
+  type Output = Result<T, JoinError>;
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Self::Output>
+  {
+    Pin::new(&mut self.0).poll(cx).map_err(|err| JoinError {
+      panicked: err.is_panic(),
+    })
+  }
+}
@@ -0,0 +1,62 @@
+use std::{
+  future::Future,
+  pin::Pin,
+  task::{
+    Context,
+    Poll,
+  },
+};
+
+use super::runtime::{
+  self,
+  JoinError,
+  JoinHandle,
+};
+
+// A task spawned on behalf of a running session. Unlike a plain runtime
+// task, it is aborted as soon as its handle is dropped, so that
+// aborting a session also tears down every task it has spawned, down
+// to the whole task tree.
+pub struct ChildTask<T>
+{
+  handle: JoinHandle<T>,
+}
+
+pub fn spawn_child<Fut>(future: Fut) -> ChildTask<Fut::Output>
+where
+  Fut: Future + Send + 'static,
+  Fut::Output: Send + 'static,
+{
+  ChildTask {
+    handle: runtime::spawn(future),
+  }
+}
+
+impl<T> ChildTask<T>
+{
+  pub fn abort(&self)
+  {
+    self.handle.abort();
+  }
+}
+
+impl<T> Future for ChildTask<T>
+{
+  type Output = Result<T, JoinError>;
+
+  fn poll(
+    mut self: Pin<&mut Self>,
+    cx: &mut Context<'_>,
+  ) -> Poll<Self::Output>
+  {
+    Pin::new(&mut self.handle).poll(cx)
+  }
+}
+
+impl<T> Drop for ChildTask<T>
+{
+  fn drop(&mut self)
+  {
+    self.handle.abort();
+  }
+}
@@ -1,28 +1,84 @@
 use std::{
-  future::Future,
+  future::{
+    poll_fn,
+    Future,
+  },
   pin::Pin,
+  task::{
+    self,
+    Poll,
+  },
+};
+
+use futures::stream::{
+  FuturesUnordered,
+  Stream,
 };
 
 use crate::internal::base::{
   channel::SenderOnce,
   context::Context,
+  describe::type_label,
+  error::SessionError,
   protocol::Protocol,
+  trace::StepSpan,
 };
 
 pub type Session<P> = PartialSession<(), P>;
 
+const STEPS_PER_POLL: usize = 64;
+
+type StepFuture =
+  Pin<Box<dyn Future<Output = Result<SessionStep, SessionError>> + Send>>;
+
+type ForwardTask =
+  Pin<Box<dyn Future<Output = Result<(), SessionError>> + Send>>;
+
 pub struct PartialSession<C, A>
 where
   A: Protocol,
   C: Context,
 {
-  executor: Box<
-    dyn FnOnce(
-        C::Endpoints,
-        SenderOnce<A>,
-      ) -> Pin<Box<dyn Future<Output = ()> + Send>>
-      + Send,
-  >,
+  executor: Box<dyn FnOnce(C::Endpoints, SenderOnce<A>) -> StepFuture + Send>,
+}
+
+// What a session executor leaves behind when it is done with its own
+// work: the continuation to run next, if any, and small forwarding
+// steps that only move a value from one channel to another. Both are
+// run inline by unsafe_run_session, which keeps the stack depth
+// constant however long a chain of continuations grows, and spares
+// spawning a task for each forwarding step.
+pub struct SessionStep
+{
+  next: Option<NextStep>,
+  forwards: Vec<ForwardTask>,
+}
+
+struct NextStep
+{
+  future: StepFuture,
+  protocol: fn() -> String,
+}
+
+impl SessionStep
+{
+  pub fn done() -> SessionStep
+  {
+    SessionStep {
+      next: None,
+      forwards: Vec::new(),
+    }
+  }
+
+  pub fn with_forward(
+    mut self,
+    forward: impl Future<Output = Result<(), SessionError>> + Send + 'static,
+  ) -> SessionStep
+  {
+    self.forwards.push(Box::pin(forward));
+
+    self
+  }
 }
 
 pub fn unsafe_create_session<C, A, Fut>(
@@ -31,22 +87,47 @@ pub fn unsafe_create_session<C, A, Fut>(
 where
   A: Protocol,
   C: Context,
-  Fut: Future<Output = ()> + Send,
-{
-  let executor2: Box<
-    dyn FnOnce(
-        C::Endpoints,
-        SenderOnce<A>,
-      ) -> Pin<Box<dyn Future<Output = ()> + Send>>
-      + Send,
-  > = Box::new(move |ctx, sender| {
-    Box::pin(async {
-      executor(ctx, sender).await;
-    })
-  });
+  Fut: Future<Output = Result<(), SessionError>> + Send,
+{
+  unsafe_create_step_session(move |ctx, sender| async move {
+    executor(ctx, sender).await?;
 
+    Ok(SessionStep::done())
+  })
+}
+
+pub fn unsafe_create_step_session<C, A, Fut>(
+  executor: impl FnOnce(C::Endpoints, SenderOnce<A>) -> Fut + Send + 'static
+) -> PartialSession<C, A>
+where
+  A: Protocol,
+  C: Context,
+  Fut: Future<Output = Result<SessionStep, SessionError>> + Send,
+{
   PartialSession {
-    executor: executor2,
+    executor: Box::new(move |ctx, sender| {
+      Box::pin(async { executor(ctx, sender).await })
+    }),
+  }
+}
+
+// Continue as the given session once the current step returns, without
+// nesting its future inside the current one.
+pub fn unsafe_continue_session<C, A>(
+  session: PartialSession<C, A>,
+  ctx: C::Endpoints,
+  sender: SenderOnce<A>,
+) -> SessionStep
+where
+  A: Protocol,
+  C: Context,
+{
+  SessionStep {
+    next: Some(NextStep {
+      future: (session.executor)(ctx, sender),
+      protocol: type_label::<A>,
+    }),
+    forwards: Vec::new(),
   }
 }
 
@@ -54,9 +135,90 @@ pub async fn unsafe_run_session<C, A>(
   session: PartialSession<C, A>,
   ctx: C::Endpoints,
   sender: SenderOnce<A>,
-) where
+) -> Result<(), SessionError>
+where
   A: Protocol,
   C: Context,
 {
-  (session.executor)(ctx, sender).await;
+  let run = StepSpan::run();
+
+  let mut index = 0;
+
+  let mut current = Some((
+    (session.executor)(ctx, sender),
+    run.session(index, type_label::<A>),
+  ));
+
+  // Forwarding steps are only polled again once they are woken up, so
+  // that the ones left waiting on a slow peer cost nothing.
+  let mut forwards: FuturesUnordered<ForwardTask> = FuturesUnordered::new();
+
+  poll_fn(move |cx| {
+    // Steps that never have to wait, such as a producer that sends a
+    // long stream of values, would otherwise keep running without
+    // giving other tasks a chance to run.
+    let mut steps = 0;
+
+    let _run = run.enter();
+
+    while let Some((future, span)) = current.as_mut() {
+      if steps == STEPS_PER_POLL {
+        cx.waker().wake_by_ref();
+
+        break;
+      }
+
+      steps += 1;
+
+      let res = {
+        let _span = span.enter();
+
+        future.as_mut().poll(cx)
+      };
+
+      match res {
+        Poll::Ready(Ok(step)) => {
+          current = step.next.map(|next| {
+            index += 1;
+
+            (next.future, run.session(index, next.protocol))
+          });
+
+          forwards.extend(step.forwards);
+        }
+        Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+        Poll::Pending => break,
+      }
+
+      // Most forwarding steps are ready right after the step that
+      // follows them, so run them before they can pile up.
+      if let Poll::Ready(Err(err)) = poll_forwards(&mut forwards, cx) {
+        return Poll::Ready(Err(err));
+      }
+    }
+
+    match poll_forwards(&mut forwards, cx) {
+      Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+      Poll::Ready(Ok(())) if current.is_none() => Poll::Ready(Ok(())),
+      _ => Poll::Pending,
+    }
+  })
+  .await
+}
+
+// Run the forwarding steps that have been woken up, until all of them
+// are done or waiting.
+fn poll_forwards(
+  forwards: &mut FuturesUnordered<ForwardTask>,
+  cx: &mut task::Context<'_>,
+) -> Poll<Result<(), SessionError>>
+{
+  loop {
+    match Pin::new(&mut *forwards).poll_next(cx) {
+      Poll::Ready(Some(Ok(()))) => {}
+      Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(err)),
+      Poll::Ready(None) => return Poll::Ready(Ok(())),
+      Poll::Pending => return Poll::Pending,
+    }
+  }
 }
@@ -2,10 +2,14 @@ use std::{
   future::Future,
   marker::PhantomData,
   pin::Pin,
+  sync::Arc,
 };
 
+use futures::future::{
+  self,
+  Either,
+};
 use serde;
-use tokio::task;
 
 use crate::internal::base::*;
 
@@ -15,8 +19,9 @@ where
 {
   executor: Box<
     dyn FnOnce(
-        Receiver<(SenderOnce<()>, SenderOnce<S>)>,
-      ) -> Pin<Box<dyn Future<Output = ()> + Send>>
+        Receiver<AcquireRequest<S>>,
+      )
+        -> Pin<Box<dyn Future<Output = Result<(), SessionError>> + Send>>
       + Send,
   >,
 }
@@ -25,33 +30,121 @@ pub struct SharedChannel<S>
 where
   S: SharedProtocol,
 {
-  endpoint: Sender<(SenderOnce<()>, SenderOnce<S>)>,
+  endpoint: BoundedSender<AcquireRequest<S>>,
+  try_endpoint: BoundedSender<AcquireRequest<S>>,
+  options: AcquireOptions,
+  metrics: Option<Arc<dyn SharedMetrics>>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+// Try-acquires come in on a channel of their own, which the shared
+// session keeps reading while waiting acquires are held up, so that
+// whether they are turned down only depends on the session being idle.
+pub struct AcquireReceiver<S>
+{
+  waiting: BoundedReceiver<AcquireRequest<S>>,
+  trying: BoundedReceiver<AcquireRequest<S>>,
+}
 
-pub struct SerializedSharedChannel<S>
+#[derive(
+  Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize,
+)]
+pub enum AcquireMode
+{
+  Wait,
+  Try,
+}
+
+// The priority and client of an acquire are only used by the shared
+// session to pick which waiting acquirer gets the lock next. Higher
+// priorities go first, and acquires from the same client take turns
+// with those of other clients under fair scheduling.
+#[derive(
+  Clone,
+  Copy,
+  Debug,
+  Default,
+  PartialEq,
+  Eq,
+  serde::Serialize,
+  serde::Deserialize,
+)]
+pub struct AcquireOptions
+{
+  pub priority: i32,
+  pub client: Option<u64>,
+}
+
+// The granted flag is answered with true once the lock is free, or
+// false if the acquire is turned down without waiting. A granted
+// acquirer then claims the lock before the linear end is handed over,
+// so that the provider keeps the lock if the acquirer has given up in
+// the meantime. A session forwarded from another process that fails
+// is reported through failed, as the acquirer would otherwise only see
+// its peer being dropped.
+pub struct AcquireRequest<S>
+{
+  pub mode: AcquireMode,
+  pub options: AcquireOptions,
+  pub granted: SenderOnce<bool>,
+  pub claim: ReceiverOnce<()>,
+  pub linear: SenderOnce<S>,
+  pub failed: SenderOnce<ForwardError>,
+}
+
+// Each acquired session is forwarded through a linear transport of its
+// own, which is sent over the acquire transport once the lock is
+// claimed. A session that fails to be forwarded drops its transport, so
+// that the peer sees it disconnect instead of waiting on it, and nothing
+// is left over for the next acquirer.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound = "")]
+pub struct SerializedSharedChannel<S, Tr = IpcTransport>
 where
   S: SharedProtocol,
+  Tr: Transport,
 {
-  acquire_sender: IpcSender<()>,
-  acquire_receiver: IpcReceiver<()>,
-  linear_sender: OpaqueSender,
-  linear_receiver: OpaqueReceiver,
+  acquire: Tr,
+  // The protocol offered by the side that serialized the channel.
+  protocol: ProtocolDesc,
+  // Applied to the linear transport of every acquired session on the
+  // deserializing side.
+  #[serde(skip)]
+  wrap_linear: Option<fn(Tr) -> Tr>,
   phantom: PhantomData<S>,
 }
 
-impl<S> Clone for SerializedSharedChannel<S>
+impl<S, Tr> Clone for SerializedSharedChannel<S, Tr>
 where
   S: SharedProtocol,
+  Tr: Transport,
 {
   fn clone(&self) -> Self
   {
     SerializedSharedChannel {
-      acquire_sender: self.acquire_sender.clone(),
-      acquire_receiver: self.acquire_receiver.clone(),
-      linear_sender: self.linear_sender.clone(),
-      linear_receiver: self.linear_receiver.clone(),
+      acquire: self.acquire.clone(),
+      protocol: self.protocol.clone(),
+      wrap_linear: self.wrap_linear,
+      phantom: PhantomData,
+    }
+  }
+}
+
+impl<S, Tr> SerializedSharedChannel<S, Tr>
+where
+  S: SharedProtocol + Describe,
+  Tr: Transport,
+{
+  // Check the messages of every acquired session against the local
+  // protocol once the channel is deserialized, so that a remote that
+  // misbehaves fails with the step it went wrong at.
+  pub fn monitored(self) -> SerializedSharedChannel<S, MonitoredTransport<Tr>>
+  {
+    SerializedSharedChannel {
+      acquire: MonitoredTransport::unmonitored(self.acquire),
+      protocol: self.protocol,
+      wrap_linear: Some(|linear: MonitoredTransport<Tr>| {
+        MonitoredTransport::new::<S>(linear.into_inner(), Party::Client)
+      }),
       phantom: PhantomData,
     }
   }
@@ -65,70 +158,212 @@ where
   {
     SharedChannel {
       endpoint: self.endpoint.clone(),
+      try_endpoint: self.try_endpoint.clone(),
+      options: self.options,
+      metrics: self.metrics.clone(),
     }
   }
 }
 
+impl<S> SharedChannel<S>
+where
+  S: SharedProtocol,
+{
+  // Acquires made through the returned channel carry the given
+  // options, while other clones of the channel keep their own.
+  pub fn with_options(
+    self,
+    options: AcquireOptions,
+  ) -> Self
+  {
+    SharedChannel { options, ..self }
+  }
+
+  pub fn with_priority(
+    self,
+    priority: i32,
+  ) -> Self
+  {
+    let options = AcquireOptions {
+      priority,
+      ..self.options
+    };
+
+    self.with_options(options)
+  }
+
+  pub fn with_client(
+    self,
+    client: u64,
+  ) -> Self
+  {
+    let options = AcquireOptions {
+      client: Some(client),
+      ..self.options
+    };
+
+    self.with_options(options)
+  }
+
+  pub fn options(&self) -> AcquireOptions
+  {
+    self.options
+  }
+
+  // Report how long acquires made through the returned channel wait
+  // for the lock. Like options, metrics are not carried over when the
+  // channel is serialized.
+  pub fn with_metrics(
+    self,
+    metrics: Arc<dyn SharedMetrics>,
+  ) -> Self
+  {
+    SharedChannel {
+      metrics: Some(metrics),
+      ..self
+    }
+  }
+
+  pub fn metrics(&self) -> Option<&Arc<dyn SharedMetrics>>
+  {
+    self.metrics.as_ref()
+  }
+}
+
+impl<S> AcquireReceiver<S>
+{
+  // Take the next acquire of either kind, waiting ones first.
+  pub async fn recv(&self) -> Option<AcquireRequest<S>>
+  {
+    let waiting = self.waiting.recv();
+
+    let trying = self.trying.recv();
+
+    futures::pin_mut!(waiting, trying);
+
+    match future::select(waiting, trying).await {
+      Either::Left((Some(request), _)) | Either::Right((Some(request), _)) => {
+        Some(request)
+      }
+      Either::Left((None, trying)) => trying.await,
+      Either::Right((None, waiting)) => waiting.await,
+    }
+  }
+
+  pub async fn recv_try(&self) -> Option<AcquireRequest<S>>
+  {
+    self.trying.recv().await
+  }
+}
+
 pub async fn unsafe_run_shared_session<S>(
   session: SharedSession<S>,
-  receiver: Receiver<(SenderOnce<()>, SenderOnce<S>)>,
-) where
+  receiver: Receiver<AcquireRequest<S>>,
+) -> Result<(), SessionError>
+where
   S: SharedProtocol,
 {
-  (session.executor)(receiver).await;
+  (session.executor)(receiver).await
 }
 
 pub fn unsafe_create_shared_session<S, Fut>(
-  executor1: impl FnOnce(Receiver<(SenderOnce<()>, SenderOnce<S>)>) -> Fut
-    + Send
-    + 'static
+  executor1: impl FnOnce(Receiver<AcquireRequest<S>>) -> Fut + Send + 'static
 ) -> SharedSession<S>
 where
   S: SharedProtocol,
-  Fut: Future<Output = ()> + Send,
+  Fut: Future<Output = Result<(), SessionError>> + Send,
 {
   let executor: Box<
     dyn FnOnce(
-        Receiver<(SenderOnce<()>, SenderOnce<S>)>,
-      ) -> Pin<Box<dyn Future<Output = ()> + Send>>
+        Receiver<AcquireRequest<S>>,
+      )
+        -> Pin<Box<dyn Future<Output = Result<(), SessionError>> + Send>>
       + Send,
-  > = Box::new(move |sender| {
-    Box::pin(async {
-      executor1(sender).await;
-    })
-  });
+  > = Box::new(move |sender| Box::pin(async { executor1(sender).await }));
 
   SharedSession { executor }
 }
 
+// Acquire requests are passed on one at a time, so that acquirers wait
+// for the shared session to take their request, rather than have it
+// queued without bound while the session is not reading them.
 pub fn unsafe_create_shared_channel<S>(
-) -> (SharedChannel<S>, Receiver<(SenderOnce<()>, SenderOnce<S>)>)
+) -> (SharedChannel<S>, AcquireReceiver<S>)
 where
   S: SharedProtocol,
 {
-  let (sender, receiver) = unbounded();
+  let (sender1, receiver1) = bounded(1);
+
+  let (sender2, receiver2) = bounded(1);
 
-  (SharedChannel { endpoint: sender }, receiver)
+  (
+    SharedChannel {
+      endpoint: sender1,
+      try_endpoint: sender2,
+      options: AcquireOptions::default(),
+      metrics: None,
+    },
+    AcquireReceiver {
+      waiting: receiver1,
+      trying: receiver2,
+    },
+  )
 }
 
+// The request is sent right away if there is room for it, so that
+// acquires made one after another arrive in the same order. Otherwise
+// the returned future waits for room.
 pub fn unsafe_receive_shared_channel<S>(
-  session: SharedChannel<S>
-) -> (ReceiverOnce<()>, ReceiverOnce<S>)
+  session: SharedChannel<S>,
+  mode: AcquireMode,
+) -> impl Future<
+  Output = Result<
+    (
+      ReceiverOnce<bool>,
+      SenderOnce<()>,
+      ReceiverOnce<S>,
+      ReceiverOnce<ForwardError>,
+    ),
+    SessionError,
+  >,
+> + Send
 where
   S: SharedProtocol,
 {
-  let (sender1, receiver1) = once_channel::<()>();
+  let (sender1, receiver1) = once_channel::<bool>();
 
   let (sender2, receiver2) = once_channel::<S>();
 
-  session.endpoint.send((sender1, sender2)).unwrap();
+  let (sender3, receiver3) = once_channel::<()>();
+
+  let (sender4, receiver4) = once_channel::<ForwardError>();
 
-  (receiver1, receiver2)
+  let endpoint = match mode {
+    AcquireMode::Wait => session.endpoint,
+    AcquireMode::Try => session.try_endpoint,
+  };
+
+  let sent = endpoint.try_send(AcquireRequest {
+    mode,
+    options: session.options,
+    granted: sender1,
+    claim: receiver3,
+    linear: sender2,
+    failed: sender4,
+  });
+
+  async move {
+    if let Some(request) = sent? {
+      endpoint.send(request).await?;
+    }
+
+    Ok((receiver1, sender3, receiver2, receiver4))
+  }
 }
 
 impl<A> serde::Serialize for SharedChannel<A>
 where
-  A: SharedProtocol + ForwardChannel,
+  A: SharedProtocol + ForwardChannel + Describe,
 {
   fn serialize<S>(
     &self,
@@ -137,13 +372,15 @@ where
   where
     S: serde::Serializer,
   {
-    serialize_shared_channel(self.clone()).serialize(serializer)
+    let acquire = IpcTransport::channel().map_err(serde::ser::Error::custom)?;
+
+    forward_shared_channel(self.clone(), acquire).serialize(serializer)
   }
 }
 
 impl<'a, A> serde::Deserialize<'a> for SharedChannel<A>
 where
-  A: SharedProtocol + ForwardChannel,
+  A: SharedProtocol + ForwardChannel + Describe,
 {
   fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
   where
@@ -151,112 +388,278 @@ where
   {
     let channel = <SerializedSharedChannel<A>>::deserialize(deserializer)?;
 
-    Ok(deserialize_shared_channel(channel))
+    deserialize_shared_channel(channel).map_err(serde::de::Error::custom)
   }
 }
 
-fn serialize_shared_channel<S>(
-  channel: SharedChannel<S>
-) -> SerializedSharedChannel<S>
+pub fn serialize_shared_channel<S, Tr>(
+  channel: SharedChannel<S>,
+  transport: &Tr,
+) -> Result<SerializedSharedChannel<S, Tr>, ChannelError>
 where
-  S: SharedProtocol + ForwardChannel,
+  S: SharedProtocol + ForwardChannel + Describe,
+  Tr: Transport,
 {
-  let (sender1, receiver1) = ipc_channel::<()>();
+  let acquire = transport.pair()?;
 
-  let (sender2, receiver2) = ipc_channel::<()>();
+  Ok(forward_shared_channel(channel, acquire))
+}
 
-  let (sender3, receiver3) = opaque_channel();
+fn forward_shared_channel<S, Tr>(
+  channel: SharedChannel<S>,
+  (acquire1, acquire2): (Tr, Tr),
+) -> SerializedSharedChannel<S, Tr>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+  Tr: Transport,
+{
+  // Only a failure of the acquire transport ends the loop. A session
+  // that fails to be forwarded is logged, its linear transport dropped,
+  // and the next acquire served.
+  runtime::spawn(async move {
+    loop {
+      let (mode, options) = match acquire1
+        .recv::<(AcquireMode, AcquireOptions)>("acquire")
+        .await
+      {
+        Ok(signal) => signal,
+        Err(err) => {
+          debug!("[serialize_shared_channel] remote endpoint closed: {}", err);
 
-  let (sender4, receiver4) = opaque_channel();
+          break;
+        }
+      };
 
-  task::spawn(async move {
-    loop {
-      let receiver1 = receiver1.clone();
+      debug!("[serialize_shared_channel] acquiring local shared channel");
 
-      let signal = task::spawn_blocking(move || receiver1.recv())
-        .await
-        .unwrap();
+      let res = async {
+        let (receiver1, sender3, receiver2, _) = unsafe_receive_shared_channel(
+          channel.clone().with_options(options),
+          mode,
+        )
+        .await?;
+
+        let granted = receiver1.recv().await?;
+
+        Ok::<_, SessionError>((granted, sender3, receiver2))
+      }
+      .await;
+
+      // A local shared channel that can no longer be acquired is
+      // reported to the remote acquirer as a refusal.
+      let (granted, m_claim) = match res {
+        Ok((granted, sender3, receiver2)) => {
+          (granted, Some((sender3, receiver2)))
+        }
+        Err(err) => {
+          error!(
+            "[serialize_shared_channel] failed to acquire local shared channel: {}",
+            err
+          );
+
+          (false, None)
+        }
+      };
 
-      match signal {
-        Some(()) => {
-          let (sender5, receiver5) = once_channel::<()>();
+      if let Err(err) = acquire1.send("acquired", granted).await {
+        debug!("[serialize_shared_channel] remote endpoint closed: {}", err);
 
-          let (sender6, receiver6) = once_channel::<S>();
+        break;
+      }
 
-          {
-            let channel = channel.clone();
+      let (sender3, receiver2) = match m_claim {
+        Some(claim) if granted => claim,
+        _ => {
+          debug!("[serialize_shared_channel] local shared channel is busy");
 
-            let sender2 = sender2.clone();
+          continue;
+        }
+      };
 
-            let receiver3 = receiver3.clone();
+      // The remote acquirer may have given up by the time the lock is
+      // granted, in which case it is left unclaimed.
+      match acquire1.recv::<bool>("claim").await {
+        Ok(true) => {}
+        Ok(false) => {
+          debug!("[serialize_shared_channel] remote acquirer is gone");
 
-            let sender4 = sender4.clone();
+          continue;
+        }
+        Err(err) => {
+          debug!("[serialize_shared_channel] remote endpoint closed: {}", err);
 
-            debug!("[serialize_shared_channel] acquiring local shared channel");
+          break;
+        }
+      }
 
-            channel.endpoint.send((sender5, sender6)).unwrap();
+      if sender3.send(()).is_err() {
+        continue;
+      }
 
-            receiver5.recv().await.unwrap();
+      debug!("[serialize_shared_channel] acquired local shared channel");
 
-            debug!("[serialize_shared_channel] acquired local shared channel");
+      let res = async {
+        let (linear1, linear2) = acquire1.pair()?;
 
-            sender2.send(());
+        acquire1.send("linear", linear2).await?;
 
-            receiver6.forward_to(sender4, receiver3);
-          }
+        Ok::<_, ChannelError>(linear1)
+      }
+      .await;
+
+      // The local lock is given up along with receiver2, as if its
+      // acquirer had been dropped.
+      let linear1 = match res {
+        Ok(linear1) => linear1,
+        Err(err) => {
+          debug!("[serialize_shared_channel] remote endpoint closed: {}", err);
+
+          break;
         }
-        None => break,
+      };
+
+      if let Err(err) = run_forward(receiver2.forward_to(linear1)).await {
+        error!("[serialize_shared_channel] failed to forward: {}", err);
       }
     }
   });
 
   SerializedSharedChannel {
-    acquire_sender: sender1,
-    acquire_receiver: receiver2,
-    linear_sender: sender3,
-    linear_receiver: receiver4,
+    acquire: acquire2,
+    protocol: describe::<S>(),
+    wrap_linear: None,
     phantom: PhantomData,
   }
 }
 
-fn deserialize_shared_channel<S>(
-  channel: SerializedSharedChannel<S>
-) -> SharedChannel<S>
+pub fn deserialize_shared_channel<S, Tr>(
+  channel: SerializedSharedChannel<S, Tr>
+) -> Result<SharedChannel<S>, ChannelError>
+where
+  S: SharedProtocol + ForwardChannel + Describe + Send,
+  Tr: Transport,
+{
+  deserialize_shared_channel_with(channel, Compatibility::Exact)
+}
+
+// Check that the protocol offered by the peer is compatible with the
+// local one before connecting to it, as a mismatch would otherwise
+// only show up as garbled messages once the channel is acquired.
+pub fn deserialize_shared_channel_with<S, Tr>(
+  channel: SerializedSharedChannel<S, Tr>,
+  compatibility: Compatibility,
+) -> Result<SharedChannel<S>, ChannelError>
 where
-  S: SharedProtocol + ForwardChannel + Send,
+  S: SharedProtocol + ForwardChannel + Describe + Send,
+  Tr: Transport,
 {
-  let (sender1, receiver1) = unbounded::<(SenderOnce<()>, SenderOnce<S>)>();
+  let expected = describe::<S>();
 
-  task::spawn(async move {
-    loop {
-      match receiver1.recv().await {
-        Some((sender2, sender3)) => {
-          debug!(
-            "[deserialize_shared_channel] acquiring remote shared channel"
+  if !channel
+    .protocol
+    .is_compatible_with(&expected, compatibility)
+  {
+    error!(
+      "[deserialize_shared_channel] peer offers incompatible protocol {}",
+      channel.protocol
+    );
+
+    return Err(ChannelError::IncompatibleProtocol {
+      expected,
+      found: channel.protocol,
+    });
+  }
+
+  let (channel1, receiver1) = unsafe_create_shared_channel::<S>();
+
+  let SerializedSharedChannel {
+    acquire,
+    wrap_linear,
+    ..
+  } = channel;
+
+  runtime::spawn(async move {
+    while let Some(request) = receiver1.recv().await {
+      if request.granted.is_closed() {
+        debug!("[deserialize_shared_channel] skipping abandoned acquire");
+
+        continue;
+      }
+
+      debug!("[deserialize_shared_channel] acquiring remote shared channel");
+
+      let res = async {
+        acquire
+          .send("acquire", (request.mode, request.options))
+          .await?;
+
+        acquire.recv::<bool>("acquired").await
+      }
+      .await;
+
+      let granted = match res {
+        Ok(granted) => granted,
+        Err(err) => {
+          error!(
+            "[deserialize_shared_channel] remote endpoint closed: {}",
+            err
           );
 
-          channel.acquire_sender.send(());
+          break;
+        }
+      };
+
+      let _ = request.granted.send(granted);
+
+      if !granted {
+        debug!("[deserialize_shared_channel] remote shared channel is busy");
+
+        continue;
+      }
+
+      let claimed = request.claim.recv().await.is_ok();
 
-          let acquire_receiver = channel.acquire_receiver.clone();
+      if let Err(err) = acquire.send("claim", claimed).await {
+        error!(
+          "[deserialize_shared_channel] remote endpoint closed: {}",
+          err
+        );
 
-          task::spawn_blocking(move || {
-            acquire_receiver.recv().unwrap();
-          })
-          .await
-          .unwrap();
+        break;
+      }
 
-          debug!("[deserialize_shared_channel] acquired remote shared channel");
+      if !claimed {
+        debug!("[deserialize_shared_channel] local acquirer is gone");
 
-          sender2.send(()).unwrap();
+        continue;
+      }
 
-          let channel2 = channel.clone();
+      let linear = match acquire.recv::<Tr>("linear").await {
+        Ok(linear) => linear,
+        Err(err) => {
+          error!(
+            "[deserialize_shared_channel] remote endpoint closed: {}",
+            err
+          );
 
-          sender3.forward_to(channel2.linear_sender, channel2.linear_receiver);
+          break;
         }
-        None => break,
+      };
+
+      let linear = match wrap_linear {
+        Some(wrap_linear) => wrap_linear(linear),
+        None => linear,
+      };
+
+      debug!("[deserialize_shared_channel] acquired remote shared channel");
+
+      if let Err(err) = run_forward(request.linear.forward_to(linear)).await {
+        error!("[deserialize_shared_channel] failed to forward: {}", err);
+
+        let _ = request.failed.send(err);
       }
     }
   });
 
-  SharedChannel { endpoint: sender1 }
+  Ok(channel1)
 }
@@ -0,0 +1,810 @@
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  future::Future,
+  io,
+  marker::PhantomData,
+  pin::Pin,
+  sync::{
+    atomic::{
+      AtomicBool,
+      AtomicU64,
+      Ordering,
+    },
+    Arc,
+    Mutex,
+  },
+};
+
+use serde::{
+  self,
+  de::Error as _,
+  ser::Error as _,
+  Deserialize,
+  Serialize,
+};
+use tokio::{
+  io::{
+    split,
+    AsyncRead,
+    AsyncReadExt,
+    AsyncWrite,
+    AsyncWriteExt,
+  },
+  net::{
+    TcpListener,
+    TcpStream,
+  },
+};
+
+use super::{
+  channel::{
+    run_forward,
+    unbounded,
+    ForwardChannel,
+    Receiver,
+    Sender,
+  },
+  describe::{
+    describe,
+    Compatibility,
+    Describe,
+    ProtocolDesc,
+  },
+  error::{
+    ChannelError,
+    ForwardError,
+  },
+  format::{
+    Bincode,
+    WireFormat,
+  },
+  protocol::SharedProtocol,
+  runtime,
+  shared::{
+    unsafe_create_shared_channel,
+    unsafe_receive_shared_channel,
+    AcquireMode,
+    AcquireOptions,
+    AcquireRequest,
+    SharedChannel,
+  },
+  transport::{
+    decode_frame,
+    Frame,
+    IncomingFrame,
+    Transport,
+    TransportFuture,
+  },
+};
+
+const OPEN_FRAME: u8 = 0;
+
+const DATA_FRAME: u8 = 1;
+
+const HELLO_FRAME: u8 = 2;
+
+const ATTACH_FRAME: u8 = 3;
+
+const CLOSE_FRAME: u8 = 4;
+
+const HEADER_SIZE: usize = 13;
+
+// A stream that both peers have from the start, on which the listener
+// announces the protocol of the shared channel it serves.
+const CONTROL_STREAM: u64 = 0;
+
+// The length prefix comes from the remote peer, so it is checked
+// against this limit before any buffer is allocated for the payload.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+thread_local! {
+  static CURRENT_MULTIPLEXER: RefCell<Option<Arc<Multiplexer>>> =
+    const { RefCell::new(None) };
+}
+
+pub trait SocketListener: Send + Sync + 'static
+{
+  type Stream: AsyncRead + AsyncWrite + Send + 'static;
+
+  fn accept(
+    &self
+  ) -> Pin<Box<dyn Future<Output = io::Result<Self::Stream>> + Send + '_>>;
+}
+
+pub struct SocketTransport<F = Bincode>
+{
+  id: u64,
+  multiplexer: Arc<Multiplexer>,
+  handle: Arc<StreamHandle>,
+  format: PhantomData<F>,
+}
+
+// Shared by all clones of a transport on this side of the connection,
+// the sub-stream is closed once the last of them is dropped. The peer is
+// told as well, so that it stops waiting on the stream.
+struct StreamHandle
+{
+  id: u64,
+  multiplexer: Arc<Multiplexer>,
+}
+
+// The sender is dropped once the peer closes the stream, so that the
+// receiver ends after the frames that arrived before.
+type SubStream = (Option<Sender<Vec<u8>>>, Receiver<Vec<u8>>);
+
+struct Multiplexer
+{
+  frames: Sender<Vec<u8>>,
+  streams: Mutex<HashMap<u64, SubStream>>,
+  next_id: AtomicU64,
+  closed: AtomicBool,
+}
+
+impl SocketListener for TcpListener
+{
+  type Stream = TcpStream;
+
+  fn accept(
+    &self
+  ) -> Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send + '_>>
+  {
+    Box::pin(async move {
+      let (stream, _) = TcpListener::accept(self).await?;
+
+      Ok(stream)
+    })
+  }
+}
+
+#[cfg(unix)]
+impl SocketListener for tokio::net::UnixListener
+{
+  type Stream = tokio::net::UnixStream;
+
+  fn accept(
+    &self
+  ) -> Pin<
+    Box<dyn Future<Output = io::Result<tokio::net::UnixStream>> + Send + '_>,
+  >
+  {
+    Box::pin(async move {
+      let (stream, _) = tokio::net::UnixListener::accept(self).await?;
+
+      Ok(stream)
+    })
+  }
+}
+
+impl<F> Clone for SocketTransport<F>
+{
+  fn clone(&self) -> Self
+  {
+    SocketTransport {
+      id: self.id,
+      multiplexer: self.multiplexer.clone(),
+      handle: self.handle.clone(),
+      format: PhantomData,
+    }
+  }
+}
+
+impl Multiplexer
+{
+  fn start<St>(
+    stream: St,
+    is_listener: bool,
+    format: &'static str,
+  ) -> (Arc<Multiplexer>, Receiver<u64>)
+  where
+    St: AsyncRead + AsyncWrite + Send + 'static,
+  {
+    let (mut reader, mut writer) = split(stream);
+
+    let (frame_sender, frame_receiver) = unbounded::<Vec<u8>>();
+
+    let (incoming_sender, incoming_receiver) = unbounded();
+
+    // Each side allocates stream ids of its own parity so that
+    // sub-channels opened concurrently by both peers never collide.
+    let multiplexer = Arc::new(Multiplexer {
+      frames: frame_sender,
+      streams: Mutex::new(HashMap::new()),
+      next_id: AtomicU64::new(if is_listener { 2 } else { 1 }),
+      closed: AtomicBool::new(false),
+    });
+
+    multiplexer.register(CONTROL_STREAM);
+
+    runtime::spawn(async move {
+      while let Some(frame) = frame_receiver.recv().await {
+        if let Err(err) = writer.write_all(&frame).await {
+          debug!("[Multiplexer] failed to write frame: {}", err);
+
+          break;
+        }
+      }
+    });
+
+    // Both peers announce their wire format before anything else,
+    // so that a mismatch is reported instead of producing garbage.
+    let _ = multiplexer.send_frame(HELLO_FRAME, 0, format.as_bytes());
+
+    let multiplexer2 = multiplexer.clone();
+
+    runtime::spawn(async move {
+      let res = async {
+        let mut greeted = false;
+
+        loop {
+          let mut header = [0; HEADER_SIZE];
+
+          reader.read_exact(&mut header).await?;
+
+          let mut id = [0; 8];
+
+          let mut len = [0; 4];
+
+          id.copy_from_slice(&header[1..9]);
+
+          len.copy_from_slice(&header[9..13]);
+
+          let id = u64::from_be_bytes(id);
+
+          let len = u32::from_be_bytes(len) as usize;
+
+          if len > MAX_FRAME_SIZE {
+            return Err(ChannelError::ProtocolDesync(format!(
+              "socket frame of {} bytes exceeds the maximum of {}",
+              len, MAX_FRAME_SIZE
+            )));
+          }
+
+          let mut payload = vec![0; len];
+
+          reader.read_exact(&mut payload).await?;
+
+          match header[0] {
+            HELLO_FRAME => {
+              if payload != format.as_bytes() {
+                return Err(ChannelError::ProtocolDesync(format!(
+                  "peer uses {} wire format, expected {}",
+                  String::from_utf8_lossy(&payload),
+                  format
+                )));
+              }
+
+              greeted = true;
+            }
+            _ if !greeted => {
+              return Err(ChannelError::ProtocolDesync(
+                "peer did not announce its wire format".to_string(),
+              ));
+            }
+            OPEN_FRAME => {
+              multiplexer2.register(id);
+
+              incoming_sender.send(id)?;
+            }
+            ATTACH_FRAME => {
+              multiplexer2.register(id);
+            }
+            CLOSE_FRAME => {
+              multiplexer2.end_stream(id);
+            }
+            DATA_FRAME => match multiplexer2.stream(id) {
+              // The local end may already be gone, in which case the
+              // frame is simply dropped.
+              Some((Some(sender), _)) => {
+                let _ = sender.send(payload);
+              }
+              _ => {
+                debug!("[Multiplexer] dropping frame for closed stream {}", id);
+              }
+            },
+            kind => {
+              return Err(ChannelError::ProtocolDesync(format!(
+                "unknown socket frame kind {}",
+                kind
+              )));
+            }
+          }
+        }
+      }
+      .await;
+
+      let res: Result<(), ChannelError> = res;
+
+      if let Err(err) = res {
+        debug!("[Multiplexer] connection closed: {}", err);
+      }
+
+      multiplexer2.closed.store(true, Ordering::SeqCst);
+
+      multiplexer2.streams.lock().unwrap().clear();
+    });
+
+    (multiplexer, incoming_receiver)
+  }
+
+  // Sub-streams are registered when they are opened or attached, and
+  // only looked up afterwards so that a late frame cannot bring a closed
+  // stream back.
+  fn register(
+    &self,
+    id: u64,
+  )
+  {
+    if self.closed.load(Ordering::SeqCst) {
+      return;
+    }
+
+    self
+      .streams
+      .lock()
+      .unwrap()
+      .entry(id)
+      .or_insert_with(|| {
+        let (sender, receiver) = unbounded();
+
+        (Some(sender), receiver)
+      });
+  }
+
+  fn stream(
+    &self,
+    id: u64,
+  ) -> Option<SubStream>
+  {
+    self.streams.lock().unwrap().get(&id).cloned()
+  }
+
+  fn close_stream(
+    &self,
+    id: u64,
+  )
+  {
+    self.streams.lock().unwrap().remove(&id);
+  }
+
+  // Frames that arrived before the peer closed the stream are still
+  // received, after which receiving on it fails as disconnected.
+  fn end_stream(
+    &self,
+    id: u64,
+  )
+  {
+    if let Some((sender, _)) = self.streams.lock().unwrap().get_mut(&id) {
+      *sender = None;
+    }
+  }
+
+  fn next_id(&self) -> u64
+  {
+    self.next_id.fetch_add(2, Ordering::SeqCst)
+  }
+
+  fn send_frame(
+    &self,
+    kind: u8,
+    id: u64,
+    payload: &[u8],
+  ) -> Result<(), ChannelError>
+  {
+    if payload.len() > MAX_FRAME_SIZE {
+      return Err(ChannelError::ProtocolDesync(format!(
+        "socket frame of {} bytes exceeds the maximum of {}",
+        payload.len(),
+        MAX_FRAME_SIZE
+      )));
+    }
+
+    let mut frame = Vec::with_capacity(HEADER_SIZE + payload.len());
+
+    frame.push(kind);
+
+    frame.extend_from_slice(&id.to_be_bytes());
+
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+
+    frame.extend_from_slice(payload);
+
+    self
+      .frames
+      .send(frame)
+      .map_err(|_| ChannelError::Disconnected)
+  }
+}
+
+impl Drop for StreamHandle
+{
+  fn drop(&mut self)
+  {
+    self.multiplexer.close_stream(self.id);
+
+    // The control stream lasts as long as the connection.
+    if self.id != CONTROL_STREAM {
+      let _ = self.multiplexer.send_frame(CLOSE_FRAME, self.id, &[]);
+    }
+  }
+}
+
+impl<F> SocketTransport<F>
+{
+  fn new(
+    id: u64,
+    multiplexer: Arc<Multiplexer>,
+  ) -> Self
+  {
+    SocketTransport {
+      id,
+      multiplexer: multiplexer.clone(),
+      handle: Arc::new(StreamHandle { id, multiplexer }),
+      format: PhantomData,
+    }
+  }
+
+  fn open(multiplexer: &Arc<Multiplexer>) -> Result<Self, ChannelError>
+  {
+    let transport =
+      SocketTransport::new(multiplexer.next_id(), multiplexer.clone());
+
+    multiplexer.register(transport.id);
+
+    multiplexer.send_frame(OPEN_FRAME, transport.id, &[])?;
+
+    Ok(transport)
+  }
+
+  fn with_multiplexer<R>(
+    &self,
+    cont: impl FnOnce() -> R,
+  ) -> R
+  {
+    let prev = CURRENT_MULTIPLEXER
+      .with(|current| current.replace(Some(self.multiplexer.clone())));
+
+    let res = cont();
+
+    CURRENT_MULTIPLEXER.with(|current| current.replace(prev));
+
+    res
+  }
+}
+
+impl<F> Transport for SocketTransport<F>
+where
+  F: WireFormat,
+{
+  fn pair(&self) -> Result<(Self, Self), ChannelError>
+  {
+    let transport = SocketTransport::new(
+      self.multiplexer.next_id(),
+      self.multiplexer.clone(),
+    );
+
+    self.multiplexer.register(transport.id);
+
+    Ok((transport.clone(), transport))
+  }
+
+  fn send<T>(
+    &self,
+    step: &'static str,
+    val: T,
+  ) -> TransportFuture<()>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let res = self
+      .with_multiplexer(|| F::encode(&Frame::new(step, val)))
+      .and_then(|payload| {
+        self.multiplexer.send_frame(DATA_FRAME, self.id, &payload)
+      });
+
+    Box::pin(async move { res })
+  }
+
+  fn recv<T>(
+    &self,
+    step: &'static str,
+  ) -> TransportFuture<T>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let transport = self.clone();
+
+    Box::pin(async move {
+      let (_, receiver) = transport
+        .multiplexer
+        .stream(transport.id)
+        .ok_or(ChannelError::Disconnected)?;
+
+      let payload = receiver.recv().await.ok_or(ChannelError::Disconnected)?;
+
+      transport.with_multiplexer(|| {
+        decode_frame(step, || F::decode::<IncomingFrame<T>>(&payload))
+      })
+    })
+  }
+}
+
+impl<F> serde::Serialize for SocketTransport<F>
+{
+  fn serialize<S>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    let same_connection = CURRENT_MULTIPLEXER.with(|current| {
+      current
+        .borrow()
+        .as_ref()
+        .is_some_and(|multiplexer| Arc::ptr_eq(multiplexer, &self.multiplexer))
+    });
+
+    if same_connection {
+      // The peer registers the stream before the frame carrying it
+      // arrives, so that nothing sent on it in between is dropped.
+      self
+        .multiplexer
+        .send_frame(ATTACH_FRAME, self.id, &[])
+        .map_err(S::Error::custom)?;
+
+      self.id.serialize(serializer)
+    } else {
+      Err(S::Error::custom(
+        "socket transport can only be sent over its own connection",
+      ))
+    }
+  }
+}
+
+impl<'a, F> serde::Deserialize<'a> for SocketTransport<F>
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'a>,
+  {
+    let id = u64::deserialize(deserializer)?;
+
+    let multiplexer = CURRENT_MULTIPLEXER
+      .with(|current| current.borrow().clone())
+      .ok_or_else(|| {
+        D::Error::custom(
+          "socket transport can only be received over its own connection",
+        )
+      })?;
+
+    Ok(SocketTransport::new(id, multiplexer))
+  }
+}
+
+pub async fn listen_shared_channel<S, L, F>(
+  channel: SharedChannel<S>,
+  listener: L,
+  format: F,
+) -> Result<(), ChannelError>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+  L: SocketListener,
+  F: WireFormat,
+{
+  loop {
+    let stream = listener.accept().await?;
+
+    runtime::spawn(serve_shared_channel(channel.clone(), stream, format));
+  }
+}
+
+pub async fn serve_shared_channel<S, St, F>(
+  channel: SharedChannel<S>,
+  stream: St,
+  _format: F,
+) -> Result<(), ChannelError>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+  St: AsyncRead + AsyncWrite + Send + 'static,
+  F: WireFormat,
+{
+  let (multiplexer, incoming) = Multiplexer::start(stream, true, F::NAME);
+
+  SocketTransport::<F>::new(CONTROL_STREAM, multiplexer.clone())
+    .send("protocol", describe::<S>())
+    .await?;
+
+  while let Some(id) = incoming.recv().await {
+    let channel = channel.clone();
+
+    let transport = SocketTransport::<F>::new(id, multiplexer.clone());
+
+    runtime::spawn(async move {
+      let res = async {
+        let (mode, options) = transport
+          .recv::<(AcquireMode, AcquireOptions)>("acquire")
+          .await
+          .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+        debug!("[serve_shared_channel] acquiring local shared channel");
+
+        let (receiver1, sender3, receiver2, _) =
+          unsafe_receive_shared_channel(channel.with_options(options), mode)
+            .await
+            .map_err(|_| {
+              ForwardError::new::<SharedChannel<S>>(ChannelError::Closed)
+            })?;
+
+        let granted = receiver1
+          .recv()
+          .await
+          .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+        transport
+          .send("acquired", granted)
+          .await
+          .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+        if !granted {
+          debug!("[serve_shared_channel] local shared channel is busy");
+
+          return Ok(());
+        }
+
+        let claimed = transport
+          .recv::<bool>("claim")
+          .await
+          .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+        if !claimed {
+          debug!("[serve_shared_channel] remote acquirer is gone");
+
+          return Ok(());
+        }
+
+        sender3
+          .send(())
+          .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+        debug!("[serve_shared_channel] acquired local shared channel");
+
+        run_forward(receiver2.forward_to(transport.clone())).await
+      }
+      .await;
+
+      // Closing the stream tells the remote acquirer that the session
+      // is over, instead of leaving it waiting on the next message.
+      if let Err(err) = res {
+        error!("[serve_shared_channel] failed to forward: {}", err);
+
+        drop(transport);
+      }
+    });
+  }
+
+  Ok(())
+}
+
+pub async fn connect_shared_channel<S, St, F>(
+  stream: St,
+  format: F,
+) -> Result<SharedChannel<S>, ChannelError>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+  St: AsyncRead + AsyncWrite + Send + 'static,
+  F: WireFormat,
+{
+  connect_shared_channel_with(stream, format, Compatibility::Exact).await
+}
+
+// Check the protocol announced by the listener before connecting to
+// it, the same way as `deserialize_shared_channel_with` does.
+pub async fn connect_shared_channel_with<S, St, F>(
+  stream: St,
+  _format: F,
+  compatibility: Compatibility,
+) -> Result<SharedChannel<S>, ChannelError>
+where
+  S: SharedProtocol + ForwardChannel + Describe,
+  St: AsyncRead + AsyncWrite + Send + 'static,
+  F: WireFormat,
+{
+  let (multiplexer, _) = Multiplexer::start(stream, false, F::NAME);
+
+  let protocol = SocketTransport::<F>::new(CONTROL_STREAM, multiplexer.clone())
+    .recv::<ProtocolDesc>("protocol")
+    .await?;
+
+  let expected = describe::<S>();
+
+  if !protocol.is_compatible_with(&expected, compatibility) {
+    error!(
+      "[connect_shared_channel] peer offers incompatible protocol {}",
+      protocol
+    );
+
+    return Err(ChannelError::IncompatibleProtocol {
+      expected,
+      found: protocol,
+    });
+  }
+
+  let (channel, receiver) = unsafe_create_shared_channel::<S>();
+
+  runtime::spawn(async move {
+    while let Some(request) = receiver.recv().await {
+      let multiplexer = multiplexer.clone();
+
+      let AcquireRequest {
+        mode,
+        options,
+        granted,
+        claim,
+        linear,
+        failed,
+      } = request;
+
+      runtime::spawn(async move {
+        let res = async {
+          if granted.is_closed() {
+            debug!("[connect_shared_channel] skipping abandoned acquire");
+
+            return Ok(());
+          }
+
+          debug!("[connect_shared_channel] acquiring remote shared channel");
+
+          let transport = SocketTransport::<F>::open(&multiplexer)
+            .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+          transport
+            .send("acquire", (mode, options))
+            .await
+            .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+          let acquired = transport
+            .recv::<bool>("acquired")
+            .await
+            .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+          let _ = granted.send(acquired);
+
+          if !acquired {
+            debug!("[connect_shared_channel] remote shared channel is busy");
+
+            return Ok(());
+          }
+
+          let claimed = claim.recv().await.is_ok();
+
+          transport
+            .send("claim", claimed)
+            .await
+            .map_err(ForwardError::new::<SharedChannel<S>>)?;
+
+          if !claimed {
+            debug!("[connect_shared_channel] local acquirer is gone");
+
+            return Ok(());
+          }
+
+          debug!("[connect_shared_channel] acquired remote shared channel");
+
+          run_forward(linear.forward_to(transport.clone())).await
+        }
+        .await;
+
+        if let Err(err) = res {
+          error!("[connect_shared_channel] failed to forward: {}", err);
+
+          let _ = failed.send(err);
+        }
+      });
+    }
+  });
+
+  Ok(channel)
+}
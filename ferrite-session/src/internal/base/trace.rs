@@ -0,0 +1,152 @@
+/*
+   With the `tracing` feature enabled, a session run is recorded as a
+   tree of spans at the TRACE level:
+
+     run_session                   one for each call to unsafe_run_session
+       session { step, protocol }  one for each partial session executed
+         action { action, slot }   a send, receive, choice, acquire or
+                                   release done by that partial session
+
+   Tasks spawned through the runtime stay under the span they were
+   spawned from. Without the feature, everything here compiles down to
+   nothing.
+*/
+
+use std::{
+  future::Future,
+  marker::PhantomData,
+};
+
+#[cfg(feature = "tracing")]
+use tracing::{
+  field,
+  span::Entered,
+  Instrument,
+  Level,
+  Span,
+};
+
+#[derive(Clone)]
+pub struct StepSpan
+{
+  #[cfg(feature = "tracing")]
+  span: Span,
+}
+
+pub struct StepGuard<'a>
+{
+  #[cfg(feature = "tracing")]
+  _entered: Entered<'a>,
+  phantom: PhantomData<&'a ()>,
+}
+
+impl StepSpan
+{
+  pub fn run() -> StepSpan
+  {
+    StepSpan {
+      #[cfg(feature = "tracing")]
+      span: tracing::span!(Level::TRACE, "run_session"),
+    }
+  }
+
+  // The protocol label is only computed when the span is recorded, as
+  // it takes an allocation.
+  #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+  pub fn session(
+    &self,
+    step: usize,
+    protocol: fn() -> String,
+  ) -> StepSpan
+  {
+    #[cfg(feature = "tracing")]
+    {
+      let span = tracing::span!(
+        parent: &self.span,
+        Level::TRACE,
+        "session",
+        step,
+        protocol = field::Empty,
+      );
+
+      if !span.is_disabled() {
+        span.record("protocol", protocol().as_str());
+      }
+
+      StepSpan { span }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    StepSpan {}
+  }
+
+  pub fn enter(&self) -> StepGuard<'_>
+  {
+    StepGuard {
+      #[cfg(feature = "tracing")]
+      _entered: self.span.enter(),
+      phantom: PhantomData,
+    }
+  }
+}
+
+// Run the future inside a span for the given action. The slot is the
+// index of the channel in the context that the action is done on, if
+// any.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub fn trace_action<Fut>(
+  action: &'static str,
+  slot: Option<usize>,
+  future: Fut,
+) -> impl Future<Output = Fut::Output>
+where
+  Fut: Future,
+{
+  #[cfg(feature = "tracing")]
+  {
+    let span =
+      tracing::span!(Level::TRACE, "action", action, slot = field::Empty);
+
+    if let Some(slot) = slot {
+      span.record("slot", slot);
+    }
+
+    future.instrument(span)
+  }
+
+  #[cfg(not(feature = "tracing"))]
+  future
+}
+
+// Run a shared session under a root span of its own, so that each
+// lock it hands out is traced as a sibling of the previous one rather
+// than nested under the session that released it.
+pub fn trace_shared<Fut>(future: Fut) -> impl Future<Output = Fut::Output>
+where
+  Fut: Future,
+{
+  #[cfg(feature = "tracing")]
+  {
+    future.instrument(tracing::span!(
+      parent: None,
+      Level::TRACE,
+      "shared_session"
+    ))
+  }
+
+  #[cfg(not(feature = "tracing"))]
+  future
+}
+
+pub fn in_current_span<Fut>(future: Fut) -> impl Future<Output = Fut::Output>
+where
+  Fut: Future,
+{
+  #[cfg(feature = "tracing")]
+  {
+    future.in_current_span()
+  }
+
+  #[cfg(not(feature = "tracing"))]
+  future
+}
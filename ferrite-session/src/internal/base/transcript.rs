@@ -0,0 +1,611 @@
+use std::{
+  cell::RefCell,
+  collections::{
+    HashMap,
+    VecDeque,
+  },
+  fmt,
+  marker::PhantomData,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+
+use serde::{
+  self,
+  de::Error as _,
+  Deserialize,
+  Serialize,
+};
+
+use super::{
+  channel::{
+    once_channel,
+    run_forward,
+    unbounded,
+    ForwardChannel,
+    Receiver,
+    Sender,
+  },
+  error::{
+    ChannelError,
+    SessionError,
+  },
+  format::{
+    Bincode,
+    WireFormat,
+  },
+  protocol::Protocol,
+  scope::spawn_child,
+  session::{
+    unsafe_create_session,
+    unsafe_run_session,
+    Session,
+  },
+  transport::{
+    Transport,
+    TransportFuture,
+  },
+};
+
+thread_local! {
+  static CURRENT_HUB: RefCell<Option<Arc<Hub>>> = const { RefCell::new(None) };
+}
+
+/*
+   A transcript is the ordered list of messages exchanged with a
+   provider, as produced by forwarding its channel through a
+   TranscriptTransport. Values, selected choices, passed channels and
+   termination each show up as a message with the name of the protocol
+   step it belongs to, and its payload encoded in the wire format.
+
+   A passed channel gets a stream of its own, identified by the path of
+   streams it was opened on. Messages are only ordered within a stream,
+   as the streams of passed channels run concurrently.
+*/
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transcript
+{
+  pub format: String,
+  pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptEntry
+{
+  pub stream: Vec<u32>,
+  pub from: Party,
+  pub step: String,
+  pub payload: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Party
+{
+  Provider,
+  Client,
+}
+
+pub struct Recorder<F = Bincode>
+{
+  hub: Arc<Hub>,
+  format: PhantomData<F>,
+}
+
+pub struct TranscriptTransport<F = Bincode>
+{
+  stream: Vec<u32>,
+  party: Party,
+  hub: Arc<Hub>,
+  format: PhantomData<F>,
+}
+
+type Message = (String, Vec<u8>);
+
+type StreamKey = (Vec<u32>, Party);
+
+struct Hub
+{
+  state: Mutex<HubState>,
+}
+
+struct HubState
+{
+  children: HashMap<StreamKey, u32>,
+  mode: Mode,
+}
+
+enum Mode
+{
+  // Messages are passed on to the other party, and appended to the
+  // transcript.
+  Record
+  {
+    entries: Vec<TranscriptEntry>,
+    inboxes: HashMap<StreamKey, (Sender<Message>, Receiver<Message>)>,
+  },
+  // Only the provider is live. Its messages are checked against the
+  // ones in the transcript, and the client's are taken from it. Each
+  // stream keeps the messages of both parties in a single queue, so
+  // that they are replayed in the order they were recorded in.
+  Replay
+  {
+    pending: HashMap<Vec<u32>, VecDeque<TranscriptEntry>>,
+  },
+}
+
+impl Party
+{
+  pub(crate) fn other(self) -> Party
+  {
+    match self {
+      Party::Provider => Party::Client,
+      Party::Client => Party::Provider,
+    }
+  }
+}
+
+impl fmt::Display for Party
+{
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result
+  {
+    match self {
+      Party::Provider => write!(f, "the provider"),
+      Party::Client => write!(f, "the client"),
+    }
+  }
+}
+
+impl Hub
+{
+  fn new(mode: Mode) -> Arc<Hub>
+  {
+    Arc::new(Hub {
+      state: Mutex::new(HubState {
+        children: HashMap::new(),
+        mode,
+      }),
+    })
+  }
+
+  // Each party numbers the streams it opens on a stream by itself, so
+  // that a stream gets the same path on every run regardless of how
+  // the two parties are scheduled.
+  fn open_stream(
+    &self,
+    stream: &[u32],
+    party: Party,
+  ) -> Vec<u32>
+  {
+    let mut state = self.state.lock().unwrap();
+
+    let next = state.children.entry((stream.to_vec(), party)).or_insert(0);
+
+    let mut child = stream.to_vec();
+
+    child.push(party as u32);
+    child.push(*next);
+
+    *next += 1;
+
+    child
+  }
+
+  fn send(
+    &self,
+    stream: &[u32],
+    from: Party,
+    step: &str,
+    payload: Vec<u8>,
+  ) -> Result<(), ChannelError>
+  {
+    let mut state = self.state.lock().unwrap();
+
+    match &mut state.mode {
+      Mode::Record { entries, inboxes } => {
+        entries.push(TranscriptEntry {
+          stream: stream.to_vec(),
+          from,
+          step: step.to_string(),
+          payload: payload.clone(),
+        });
+
+        let (sender, _) = inboxes
+          .entry((stream.to_vec(), from.other()))
+          .or_insert_with(unbounded);
+
+        sender.send((step.to_string(), payload))
+      }
+      Mode::Replay { pending } => {
+        let entries = pending.get_mut(stream);
+
+        if let Some(entry) = entries
+          .as_ref()
+          .and_then(|entries| entries.front())
+          .filter(|entry| entry.from != from)
+        {
+          return Err(ChannelError::ProtocolDesync(format!(
+            "expected {} to send {} step on stream {:?} before {} sends {}",
+            entry.from, entry.step, stream, from, step
+          )));
+        }
+
+        match entries.and_then(|entries| entries.pop_front()) {
+          Some(entry) if entry.step == step && entry.payload == payload => {
+            Ok(())
+          }
+          Some(entry) if entry.step == step => {
+            Err(ChannelError::ProtocolDesync(format!(
+              "{} sent a different {} payload than in the transcript on \
+               stream {:?}",
+              from, step, stream
+            )))
+          }
+          Some(entry) => Err(ChannelError::ProtocolDesync(format!(
+            "expected {} to send {} step on stream {:?}, got {}",
+            from, entry.step, stream, step
+          ))),
+          None => Err(ChannelError::ProtocolDesync(format!(
+            "{} sent {} step on stream {:?} past the end of the transcript",
+            from, step, stream
+          ))),
+        }
+      }
+    }
+  }
+
+  async fn recv(
+    &self,
+    stream: &[u32],
+    to: Party,
+  ) -> Result<Message, ChannelError>
+  {
+    let receiver = {
+      let mut state = self.state.lock().unwrap();
+
+      match &mut state.mode {
+        Mode::Record { inboxes, .. } => {
+          let (_, receiver) = inboxes
+            .entry((stream.to_vec(), to))
+            .or_insert_with(unbounded);
+
+          receiver.clone()
+        }
+        Mode::Replay { pending } => {
+          let entries = pending
+            .get_mut(stream)
+            .ok_or(ChannelError::Disconnected)?;
+
+          // The message can only be received once every message
+          // recorded before it on the stream has been replayed.
+          if let Some(entry) = entries.front().filter(|entry| entry.from == to)
+          {
+            return Err(ChannelError::ProtocolDesync(format!(
+              "expected {} to send {} step on stream {:?} before receiving",
+              to, entry.step, stream
+            )));
+          }
+
+          let entry = entries.pop_front().ok_or(ChannelError::Disconnected)?;
+
+          return Ok((entry.step, entry.payload));
+        }
+      }
+    };
+
+    receiver.recv().await.ok_or(ChannelError::Disconnected)
+  }
+
+  // Fail if the transcript has messages that were never replayed.
+  fn finish_replay(&self) -> Result<(), ChannelError>
+  {
+    let state = self.state.lock().unwrap();
+
+    if let Mode::Replay { pending } = &state.mode {
+      let left = pending
+        .values()
+        .flatten()
+        .min_by(|entry1, entry2| entry1.stream.cmp(&entry2.stream));
+
+      if let Some(entry) = left {
+        return Err(ChannelError::ProtocolDesync(format!(
+          "session ended before the {} step from {} on stream {:?}",
+          entry.step, entry.from, entry.stream
+        )));
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl<F> Recorder<F>
+where
+  F: WireFormat,
+{
+  pub fn transcript(&self) -> Transcript
+  {
+    let state = self.hub.state.lock().unwrap();
+
+    let entries = match &state.mode {
+      Mode::Record { entries, .. } => entries.clone(),
+      Mode::Replay { .. } => Vec::new(),
+    };
+
+    Transcript {
+      format: F::NAME.to_string(),
+      entries,
+    }
+  }
+}
+
+impl<F> TranscriptTransport<F>
+{
+  fn new(
+    stream: Vec<u32>,
+    party: Party,
+    hub: Arc<Hub>,
+  ) -> Self
+  {
+    TranscriptTransport {
+      stream,
+      party,
+      hub,
+      format: PhantomData,
+    }
+  }
+
+  fn with_hub<R>(
+    &self,
+    cont: impl FnOnce() -> R,
+  ) -> R
+  {
+    let prev =
+      CURRENT_HUB.with(|current| current.replace(Some(self.hub.clone())));
+
+    let res = cont();
+
+    CURRENT_HUB.with(|current| current.replace(prev));
+
+    res
+  }
+}
+
+impl<F> Clone for TranscriptTransport<F>
+{
+  fn clone(&self) -> Self
+  {
+    TranscriptTransport::new(self.stream.clone(), self.party, self.hub.clone())
+  }
+}
+
+impl<F> Transport for TranscriptTransport<F>
+where
+  F: WireFormat,
+{
+  // The first end stays with the party that opened the stream, and the
+  // second is meant to be sent to the other party.
+  fn pair(&self) -> Result<(Self, Self), ChannelError>
+  {
+    let stream = self.hub.open_stream(&self.stream, self.party);
+
+    Ok((
+      TranscriptTransport::new(stream.clone(), self.party, self.hub.clone()),
+      TranscriptTransport::new(stream, self.party.other(), self.hub.clone()),
+    ))
+  }
+
+  fn send<T>(
+    &self,
+    step: &'static str,
+    val: T,
+  ) -> TransportFuture<()>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let res = self.with_hub(|| F::encode(&val)).and_then(|payload| {
+      self.hub.send(&self.stream, self.party, step, payload)
+    });
+
+    Box::pin(async move { res })
+  }
+
+  fn recv<T>(
+    &self,
+    step: &'static str,
+  ) -> TransportFuture<T>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let transport = self.clone();
+
+    Box::pin(async move {
+      let (step2, payload) = transport
+        .hub
+        .recv(&transport.stream, transport.party)
+        .await?;
+
+      if step2 != step {
+        return Err(ChannelError::UnexpectedStep {
+          expected: step.to_string(),
+          found: step2,
+        });
+      }
+
+      transport.with_hub(|| F::decode(&payload))
+    })
+  }
+}
+
+impl<F> serde::Serialize for TranscriptTransport<F>
+{
+  fn serialize<S>(
+    &self,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    (&self.stream, self.party).serialize(serializer)
+  }
+}
+
+impl<'a, F> serde::Deserialize<'a> for TranscriptTransport<F>
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: serde::Deserializer<'a>,
+  {
+    let (stream, party) = <(Vec<u32>, Party)>::deserialize(deserializer)?;
+
+    let hub = CURRENT_HUB
+      .with(|current| current.borrow().clone())
+      .ok_or_else(|| {
+        D::Error::custom(
+          "transcript transport can only be received through its own \
+           transcript",
+        )
+      })?;
+
+    Ok(TranscriptTransport::new(stream, party, hub))
+  }
+}
+
+/*
+   Record every message exchanged between the given provider and the
+   client that the returned session is used by. The recorder can be
+   asked for the transcript at any time, which then holds the messages
+   exchanged so far.
+*/
+
+pub fn record_session<A, F>(session: Session<A>) -> (Session<A>, Recorder<F>)
+where
+  A: Protocol + ForwardChannel,
+  F: WireFormat,
+{
+  let hub = Hub::new(Mode::Record {
+    entries: Vec::new(),
+    inboxes: HashMap::new(),
+  });
+
+  let recorder = Recorder {
+    hub: hub.clone(),
+    format: PhantomData,
+  };
+
+  let session = unsafe_create_session(move |(), sender1| async move {
+    let provider =
+      TranscriptTransport::<F>::new(Vec::new(), Party::Provider, hub.clone());
+
+    let client = TranscriptTransport::<F>::new(Vec::new(), Party::Client, hub);
+
+    let (sender2, receiver2) = once_channel();
+
+    let child1 = spawn_child(unsafe_run_session(session, (), sender2));
+
+    let child2 = spawn_child(async move {
+      let channel = receiver2.recv().await?;
+
+      run_forward(channel.forward_to(provider))
+        .await
+        .map_err(|err| {
+          error!("[record_session] failed to forward provider: {}", err);
+
+          err.error
+        })?;
+
+      Ok::<_, SessionError>(())
+    });
+
+    let (channel, rest) = A::forward_from(client).await.map_err(|err| {
+      error!("[record_session] failed to forward client: {}", err);
+
+      err.error
+    })?;
+
+    sender1.send(channel)?;
+
+    run_forward(rest).await.map_err(|err| {
+      error!("[record_session] failed to forward client: {}", err);
+
+      err.error
+    })?;
+
+    child2.await??;
+
+    child1.await?
+  });
+
+  (session, recorder)
+}
+
+/*
+   Run the provider against a fake client that sends the client's
+   messages from the transcript, and fail as soon as the provider sends
+   anything other than what it sent when the transcript was recorded.
+*/
+
+pub async fn replay_transcript<A, F>(
+  session: Session<A>,
+  transcript: &Transcript,
+  _format: F,
+) -> Result<(), SessionError>
+where
+  A: Protocol + ForwardChannel,
+  F: WireFormat,
+{
+  if transcript.format != F::NAME {
+    return Err(
+      ChannelError::ProtocolDesync(format!(
+        "transcript is recorded in {} wire format, expected {}",
+        transcript.format,
+        F::NAME
+      ))
+      .into(),
+    );
+  }
+
+  let mut pending: HashMap<Vec<u32>, VecDeque<TranscriptEntry>> =
+    HashMap::new();
+
+  for entry in &transcript.entries {
+    pending
+      .entry(entry.stream.clone())
+      .or_default()
+      .push_back(entry.clone());
+  }
+
+  let hub = Hub::new(Mode::Replay { pending });
+
+  let transport =
+    TranscriptTransport::<F>::new(Vec::new(), Party::Provider, hub.clone());
+
+  let (sender, receiver) = once_channel();
+
+  let child = spawn_child(unsafe_run_session(session, (), sender));
+
+  // The provider may be left waiting for a message that never comes
+  // once it diverges, so a failed replay does not wait for it.
+  let channel = receiver.recv().await?;
+
+  run_forward(channel.forward_to(transport))
+    .await
+    .map_err(|err| {
+      debug!("[replay_transcript] replay failed: {}", err);
+
+      err.error
+    })?;
+
+  child.await??;
+
+  hub.finish_replay()?;
+
+  Ok(())
+}
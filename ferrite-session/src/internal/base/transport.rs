@@ -0,0 +1,406 @@
+use std::{
+  borrow::Cow,
+  cell::Cell,
+  collections::VecDeque,
+  fmt,
+  future::Future,
+  marker::PhantomData,
+  pin::Pin,
+  sync::{
+    Arc,
+    Mutex,
+  },
+};
+
+use ipc_channel::ipc::OpaqueIpcMessage;
+use serde::{
+  self,
+  de::{
+    Error as _,
+    IgnoredAny,
+    MapAccess,
+    SeqAccess,
+    Visitor,
+  },
+  Deserialize,
+  Deserializer,
+  Serialize,
+};
+
+use super::{
+  channel::{
+    once_channel,
+    opaque_channel,
+    OpaqueReceiver,
+    OpaqueSender,
+    Receiver,
+  },
+  error::ChannelError,
+  runtime,
+};
+
+thread_local! {
+  static EXPECTED_STEP: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+pub type TransportFuture<T> =
+  Pin<Box<dyn Future<Output = Result<T, ChannelError>> + Send>>;
+
+pub trait Transport:
+  Clone + Send + Sync + Serialize + for<'de> Deserialize<'de> + 'static
+{
+  fn pair(&self) -> Result<(Self, Self), ChannelError>;
+
+  fn send<T>(
+    &self,
+    step: &'static str,
+    val: T,
+  ) -> TransportFuture<()>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static;
+
+  fn recv<T>(
+    &self,
+    step: &'static str,
+  ) -> TransportFuture<T>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static;
+}
+
+// Every message is wrapped with the name of the protocol step it belongs
+// to, so that peers not written in Rust can make sense of the traffic,
+// and so that a desynchronized peer is detected instead of misread.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Frame<T>
+{
+  pub step: Cow<'static, str>,
+  pub payload: T,
+}
+
+// A frame as it is decoded on arrival. The payload is only decoded if
+// the frame is at the step that is expected, as a payload of another
+// step would usually fail to decode, or worse be misread, before the
+// step could be checked. It is never sent, and is only serializable
+// because ipc-channel requires it of everything it receives.
+#[derive(serde::Serialize)]
+pub struct IncomingFrame<T>
+{
+  step: String,
+  payload: Option<T>,
+}
+
+struct IncomingFrameVisitor<T>(PhantomData<T>);
+
+type SendJob = Box<dyn FnOnce(&OpaqueSender) + Send>;
+
+// Frames waiting to be sent, in order, by a blocking task that only
+// runs while there are any.
+#[derive(Default)]
+struct Outgoing
+{
+  jobs: VecDeque<SendJob>,
+  draining: bool,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct IpcTransport
+{
+  sender: OpaqueSender,
+  receiver: OpaqueReceiver,
+  // Messages read by the ipc router, once the transport starts receiving.
+  // They are shared by all clones, so a receive that is dropped leaves
+  // nothing behind only for sessions that get a transport of their own.
+  #[serde(skip)]
+  incoming: Arc<Mutex<Option<Receiver<OpaqueIpcMessage>>>>,
+  #[serde(skip)]
+  outgoing: Arc<Mutex<Outgoing>>,
+}
+
+impl IpcTransport
+{
+  pub fn channel() -> Result<(IpcTransport, IpcTransport), ChannelError>
+  {
+    let (sender1, receiver1) = opaque_channel()?;
+
+    let (sender2, receiver2) = opaque_channel()?;
+
+    Ok((
+      IpcTransport {
+        sender: sender1,
+        receiver: receiver2,
+        incoming: Default::default(),
+        outgoing: Default::default(),
+      },
+      IpcTransport {
+        sender: sender2,
+        receiver: receiver1,
+        incoming: Default::default(),
+        outgoing: Default::default(),
+      },
+    ))
+  }
+
+  fn enqueue(
+    &self,
+    job: SendJob,
+  )
+  {
+    let mut outgoing = self.outgoing.lock().unwrap();
+
+    outgoing.jobs.push_back(job);
+
+    if !outgoing.draining {
+      outgoing.draining = true;
+
+      let sender = self.sender.clone();
+      let queue = self.outgoing.clone();
+
+      runtime::spawn_blocking(move || loop {
+        let job = {
+          let mut outgoing = queue.lock().unwrap();
+
+          match outgoing.jobs.pop_front() {
+            Some(job) => job,
+            None => {
+              outgoing.draining = false;
+              return;
+            }
+          }
+        };
+
+        job(&sender);
+      });
+    }
+  }
+
+  fn incoming(&self) -> Result<Receiver<OpaqueIpcMessage>, ChannelError>
+  {
+    let mut incoming = self.incoming.lock().unwrap();
+
+    match incoming.as_ref() {
+      Some(receiver) => Ok(receiver.clone()),
+      None => {
+        let receiver = self.receiver.route()?;
+
+        *incoming = Some(receiver.clone());
+
+        Ok(receiver)
+      }
+    }
+  }
+}
+
+impl<T> Frame<T>
+{
+  pub fn new(
+    step: &'static str,
+    payload: T,
+  ) -> Frame<T>
+  {
+    Frame {
+      step: Cow::Borrowed(step),
+      payload,
+    }
+  }
+
+  pub fn into_payload(
+    self,
+    step: &str,
+  ) -> Result<T, ChannelError>
+  {
+    if self.step == step {
+      Ok(self.payload)
+    } else {
+      Err(ChannelError::UnexpectedStep {
+        expected: step.to_string(),
+        found: self.step.into_owned(),
+      })
+    }
+  }
+}
+
+// Decode a frame that is expected to be at the given step, with the
+// decoder given in a closure so that it runs on the current thread.
+pub fn decode_frame<T>(
+  step: &'static str,
+  decode: impl FnOnce() -> Result<IncomingFrame<T>, ChannelError>,
+) -> Result<T, ChannelError>
+{
+  let prev = EXPECTED_STEP.with(|expected| expected.replace(Some(step)));
+
+  let res = decode();
+
+  EXPECTED_STEP.with(|expected| expected.set(prev));
+
+  match res? {
+    IncomingFrame {
+      payload: Some(payload),
+      ..
+    } => Ok(payload),
+    IncomingFrame { step: found, .. } => Err(ChannelError::UnexpectedStep {
+      expected: step.to_string(),
+      found,
+    }),
+  }
+}
+
+impl<T> IncomingFrameVisitor<T>
+{
+  fn is_expected(step: &str) -> bool
+  {
+    EXPECTED_STEP.with(|expected| match expected.get() {
+      Some(expected) => expected == step,
+      None => true,
+    })
+  }
+}
+
+impl<'de, T> Visitor<'de> for IncomingFrameVisitor<T>
+where
+  T: Deserialize<'de>,
+{
+  type Value = IncomingFrame<T>;
+
+  fn expecting(
+    &self,
+    f: &mut fmt::Formatter,
+  ) -> fmt::Result
+  {
+    write!(f, "a protocol frame")
+  }
+
+  fn visit_seq<A>(
+    self,
+    mut seq: A,
+  ) -> Result<Self::Value, A::Error>
+  where
+    A: SeqAccess<'de>,
+  {
+    let step: String = seq
+      .next_element()?
+      .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+
+    if Self::is_expected(&step) {
+      let payload = seq
+        .next_element()?
+        .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+
+      Ok(IncomingFrame {
+        step,
+        payload: Some(payload),
+      })
+    } else {
+      // Formats that are not self describing cannot skip the payload,
+      // but they also do not mind it being left unread.
+      let _ = seq.next_element::<IgnoredAny>();
+
+      Ok(IncomingFrame {
+        step,
+        payload: None,
+      })
+    }
+  }
+
+  fn visit_map<A>(
+    self,
+    mut map: A,
+  ) -> Result<Self::Value, A::Error>
+  where
+    A: MapAccess<'de>,
+  {
+    let mut step: Option<String> = None;
+
+    let mut payload = None;
+
+    while let Some(key) = map.next_key::<String>()? {
+      match key.as_str() {
+        "step" => {
+          step = Some(map.next_value()?);
+        }
+        "payload" if step.as_deref().map_or(true, Self::is_expected) => {
+          payload = Some(map.next_value()?);
+        }
+        _ => {
+          map.next_value::<IgnoredAny>()?;
+        }
+      }
+    }
+
+    let step = step.ok_or_else(|| A::Error::missing_field("step"))?;
+
+    if Self::is_expected(&step) && payload.is_none() {
+      return Err(A::Error::missing_field("payload"));
+    }
+
+    Ok(IncomingFrame { step, payload })
+  }
+}
+
+impl<'de, T> Deserialize<'de> for IncomingFrame<T>
+where
+  T: Deserialize<'de>,
+{
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    deserializer.deserialize_struct(
+      "Frame",
+      &["step", "payload"],
+      IncomingFrameVisitor(PhantomData),
+    )
+  }
+}
+
+impl Transport for IpcTransport
+{
+  fn pair(&self) -> Result<(Self, Self), ChannelError>
+  {
+    IpcTransport::channel()
+  }
+
+  fn send<T>(
+    &self,
+    step: &'static str,
+    val: T,
+  ) -> TransportFuture<()>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let transport = self.clone();
+
+    Box::pin(async move {
+      let (done, result) = once_channel();
+
+      transport.enqueue(Box::new(move |sender| {
+        let _ = done.send(sender.send(Frame::new(step, val)));
+      }));
+
+      result.recv().await?
+    })
+  }
+
+  fn recv<T>(
+    &self,
+    step: &'static str,
+  ) -> TransportFuture<T>
+  where
+    T: Serialize + for<'de> Deserialize<'de>,
+    T: Send + 'static,
+  {
+    let incoming = self.incoming();
+
+    Box::pin(async move {
+      let message = incoming?.recv().await.ok_or(ChannelError::Disconnected)?;
+
+      decode_frame(step, || {
+        message
+          .to::<IncomingFrame<T>>()
+          .map_err(|err| ChannelError::Serialization(err))
+      })
+    })
+  }
+}
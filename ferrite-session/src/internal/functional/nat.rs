@@ -6,6 +6,8 @@ pub trait Nat: Send + Copy + 'static
   const Value: Self;
 
   fn nat() -> Self;
+
+  fn to_usize() -> usize;
 }
 
 #[derive(Copy, Clone)]
@@ -24,6 +26,11 @@ impl Nat for Z
   {
     Z
   }
+
+  fn to_usize() -> usize
+  {
+    0
+  }
 }
 
 impl<N> Nat for S<N>
@@ -38,6 +45,11 @@ where
   {
     S(PhantomData)
   }
+
+  fn to_usize() -> usize
+  {
+    N::to_usize() + 1
+  }
 }
 
 pub fn succ<N>(_: N) -> S<N>
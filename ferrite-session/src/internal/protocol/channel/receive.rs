@@ -25,3 +25,46 @@ where
 {
   type Applied = ReceiveChannel<A, B::Applied>;
 }
+
+impl<P, Q> ForwardChannel for ReceiveChannel<P, Q>
+where
+  P: ForwardChannel,
+  Q: ForwardChannel,
+{
+  fn forward_to<Tr>(
+    self,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    self.0.forward_to(transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let (sender2, rest) =
+        <SenderOnce<(ReceiverOnce<P>, SenderOnce<Q>)>>::forward_from(transport)
+          .await?;
+
+      Ok((ReceiveChannel(sender2), rest))
+    })
+  }
+}
+
+impl<P, Q> Describe for ReceiveChannel<P, Q>
+where
+  P: Describe,
+  Q: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::ReceiveChannel(
+      Box::new(P::describe_in(ctx)),
+      Box::new(Q::describe_in(ctx)),
+    )
+  }
+}
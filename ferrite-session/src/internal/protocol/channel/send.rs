@@ -12,6 +12,13 @@ where
 {
 }
 
+impl<P, Q> PositiveProtocol for SendChannel<P, Q>
+where
+  P: Protocol,
+  Q: Protocol,
+{
+}
+
 impl<A, P, Q> RecApp<A> for SendChannel<P, Q>
 where
   P: RecApp<A>,
@@ -27,3 +34,45 @@ where
 {
   type Applied = SendChannel<P, Q::Applied>;
 }
+
+impl<P, Q> ForwardChannel for SendChannel<P, Q>
+where
+  P: ForwardChannel,
+  Q: ForwardChannel,
+{
+  fn forward_to<Tr>(
+    self,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    (self.0, self.1).forward_to(transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let ((channel, cont), rest) =
+        <(ReceiverOnce<P>, ReceiverOnce<Q>)>::forward_from(transport).await?;
+
+      Ok((SendChannel(channel, cont), rest))
+    })
+  }
+}
+
+impl<P, Q> Describe for SendChannel<P, Q>
+where
+  P: Describe,
+  Q: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::SendChannel(
+      Box::new(P::describe_in(ctx)),
+      Box::new(Q::describe_in(ctx)),
+    )
+  }
+}
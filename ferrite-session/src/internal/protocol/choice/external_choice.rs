@@ -54,25 +54,39 @@ where
   AppSum<Row2, ()>:
     Send + 'static + serde::Serialize + for<'de> serde::Deserialize<'de>,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.sender.forward_to(sender, receiver)
+    self.sender.forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    ExternalChoice {
-      sender: <SenderOnce<(
+    Box::pin(async move {
+      let (sender2, rest) = <SenderOnce<(
         Value<AppSum<Row2, ()>>,
         SenderOnce<AppSum<Row2, ReceiverF>>,
-      )>>::forward_from(sender, receiver),
-    }
+      )>>::forward_from(transport)
+      .await?;
+
+      Ok((ExternalChoice { sender: sender2 }, rest))
+    })
+  }
+}
+
+impl<Row> Describe for ExternalChoice<Row>
+where
+  Row: ToRow,
+  Row::Row: DescribeRow,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::ExternalChoice(describe_row::<Row::Row>(ctx))
   }
 }
@@ -17,6 +17,13 @@ where
 {
 }
 
+impl<Row1, Row2> PositiveProtocol for InternalChoice<Row1>
+where
+  Row1: Send + 'static,
+  Row1: ToRow<Row = Row2>,
+{
+}
+
 impl<Row1, Row2, Row3, A> RecApp<A> for InternalChoice<Row1>
 where
   A: Send + 'static,
@@ -47,22 +54,36 @@ where
   Row2: RowCon,
   AppSum<Row2, ReceiverF>: ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    self.field.forward_to(transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    self.field.forward_to(sender, receiver)
+    Box::pin(async move {
+      let (field, rest) =
+        <AppSum<Row2, ReceiverF>>::forward_from(transport).await?;
+
+      Ok((InternalChoice { field }, rest))
+    })
   }
+}
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+impl<Row> Describe for InternalChoice<Row>
+where
+  Row: ToRow,
+  Row::Row: DescribeRow,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
   {
-    InternalChoice {
-      field: <AppSum<Row2, ReceiverF>>::forward_from(sender, receiver),
-    }
+    ProtocolDesc::InternalChoice(describe_row::<Row::Row>(ctx))
   }
 }
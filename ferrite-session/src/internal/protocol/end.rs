@@ -4,6 +4,8 @@ pub struct End();
 
 impl Protocol for End {}
 
+impl PositiveProtocol for End {}
+
 impl<A> RecApp<A> for End
 {
   type Applied = End;
@@ -11,22 +13,42 @@ impl<A> RecApp<A> for End
 
 impl ForwardChannel for End
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    _: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    sender.send(())
+    ForwardStep::next(async move {
+      transport
+        .send("end", ())
+        .await
+        .map_err(ForwardError::new::<End>)?;
+
+      Ok(ForwardStep::done())
+    })
   }
 
-  fn forward_from(
-    _: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let () = receiver.recv().unwrap();
+    Box::pin(async move {
+      let () = transport
+        .recv("end")
+        .await
+        .map_err(ForwardError::new::<End>)?;
 
-    End()
+      Ok((End(), ForwardStep::done()))
+    })
+  }
+}
+
+impl Describe for End
+{
+  fn describe_in(_: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::End
   }
 }
@@ -21,22 +21,35 @@ where
   F: Send + 'static + SharedRecApp<SharedToLinear<F>, Applied = T>,
   T: Send + 'static + ForwardChannel,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.linear.forward_to(sender, receiver)
+    self.linear.forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    LinearToShared {
-      linear: T::forward_from(sender, receiver),
-    }
+    Box::pin(async move {
+      let (linear, rest) = T::forward_from(transport).await?;
+
+      Ok((LinearToShared { linear }, rest))
+    })
+  }
+}
+
+impl<F> Describe for LinearToShared<F>
+where
+  F: Describe,
+  F: SharedRecApp<SharedToLinear<F>>,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::LinearToShared(Box::new(F::describe_in(ctx)))
   }
 }
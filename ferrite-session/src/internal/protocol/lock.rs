@@ -8,7 +8,7 @@ pub struct Lock<F>
 where
   F: SharedRecApp<SharedToLinear<F>>,
 {
-  pub(crate) unlock: Receiver<(SenderOnce<()>, SenderOnce<LinearToShared<F>>)>,
+  pub(crate) unlock: Receiver<AcquireRequest<LinearToShared<F>>>,
 }
 
 impl<F> Protocol for Lock<F>
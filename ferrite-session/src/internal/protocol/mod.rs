@@ -29,6 +29,8 @@ pub use self::{
     SendValue,
   },
   wrap::{
+    DescribeWrapper,
+    ForwardWrapper,
     Wrap,
     Wrapper,
   },
@@ -1,7 +1,9 @@
 #[doc(inline)]
 pub use super::{
+  DescribeWrapper,
   End,
   ExternalChoice,
+  ForwardWrapper,
   InternalChoice,
   LinearToShared,
   ReceiveChannel,
@@ -14,25 +14,40 @@ impl<F> ForwardChannel for SharedToLinear<F>
 where
   F: Send + 'static,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.unlock.forward_to(sender, receiver);
+    self.unlock.forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    let unlock = <SenderOnce<()>>::forward_from(sender, receiver);
+    Box::pin(async move {
+      let (unlock, rest) = <SenderOnce<()>>::forward_from(transport).await?;
 
-    SharedToLinear {
-      unlock,
-      phantom: PhantomData,
-    }
+      Ok((
+        SharedToLinear {
+          unlock,
+          phantom: PhantomData,
+        },
+        rest,
+      ))
+    })
+  }
+}
+
+// The applied form of Release, which is what a linear session holds
+// once it has acquired a shared channel.
+impl<F> Describe for SharedToLinear<F>
+{
+  fn describe_in(_: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::Release
   }
 }
@@ -34,22 +34,37 @@ where
   T: Send + 'static,
   T: serde::Serialize + for<'de> serde::Deserialize<'de>,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.0.forward_to(sender, receiver)
+    self.0.forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
   {
-    ReceiveValue(<SenderOnce<(Value<T>, SenderOnce<A>)>>::forward_from(
-      sender, receiver,
-    ))
+    Box::pin(async move {
+      let (sender2, rest) =
+        <SenderOnce<(Value<T>, SenderOnce<A>)>>::forward_from(transport)
+          .await?;
+
+      Ok((ReceiveValue(sender2), rest))
+    })
+  }
+}
+
+impl<T, A> Describe for ReceiveValue<T, A>
+where
+  T: DescribeValue,
+  A: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ProtocolDesc::ReceiveValue(T::value_name(), Box::new(A::describe_in(ctx)))
   }
 }
@@ -11,6 +11,13 @@ where
 {
 }
 
+impl<T, P> PositiveProtocol for SendValue<T, P>
+where
+  T: Send + 'static,
+  P: Protocol,
+{
+}
+
 impl<X, T, A> RecApp<X> for SendValue<T, A>
 where
   T: Send + 'static,
@@ -33,22 +40,36 @@ where
   T: Send + 'static,
   T: serde::Serialize + for<'de> serde::Deserialize<'de>,
 {
-  fn forward_to(
+  fn forward_to<Tr>(
     self,
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  )
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
   {
-    self.0.forward_to(sender, receiver)
+    self.0.forward_to(transport)
   }
 
-  fn forward_from(
-    sender: OpaqueSender,
-    receiver: OpaqueReceiver,
-  ) -> Self
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let (payload, rest) =
+        <(Value<T>, ReceiverOnce<A>)>::forward_from(transport).await?;
+
+      Ok((SendValue(payload), rest))
+    })
+  }
+}
+
+impl<T, A> Describe for SendValue<T, A>
+where
+  T: DescribeValue,
+  A: Describe,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
   {
-    SendValue(<(Value<T>, ReceiverOnce<A>)>::forward_from(
-      sender, receiver,
-    ))
+    ProtocolDesc::SendValue(T::value_name(), Box::new(A::describe_in(ctx)))
   }
 }
@@ -1,4 +1,14 @@
-use base::Protocol;
+use base::{
+  Describe,
+  DescribeContext,
+  ForwardChannel,
+  ForwardFuture,
+  ForwardStep,
+  PositiveProtocol,
+  Protocol,
+  ProtocolDesc,
+  Transport,
+};
 
 use crate::internal::base;
 
@@ -7,6 +17,17 @@ pub trait Wrapper
   type Unwrap: Protocol;
 }
 
+pub trait ForwardWrapper: Wrapper<Unwrap: ForwardChannel> {}
+
+// Like ForwardWrapper, implemented by hand for each wrapper, so that
+// recursive wrappers do not send trait resolution into a cycle. The
+// name stands for the wrapper in descriptions, the same way as the
+// name of a payload type does.
+pub trait DescribeWrapper: Wrapper<Unwrap: Describe>
+{
+  const NAME: &'static str;
+}
+
 pub struct Wrap<T>
 where
   T: Wrapper,
@@ -20,3 +41,53 @@ where
   T: Send + 'static,
 {
 }
+
+impl<T> PositiveProtocol for Wrap<T>
+where
+  T: Wrapper,
+  T: Send + 'static,
+  T::Unwrap: PositiveProtocol,
+{
+}
+
+impl<T> ForwardChannel for Wrap<T>
+where
+  T: ForwardWrapper,
+  T: Send + 'static,
+{
+  fn forward_to<Tr>(
+    self,
+    transport: Tr,
+  ) -> ForwardStep
+  where
+    Tr: Transport,
+  {
+    self.unwrap.forward_to(transport)
+  }
+
+  fn forward_from<Tr>(transport: Tr) -> ForwardFuture<(Self, ForwardStep)>
+  where
+    Tr: Transport,
+  {
+    Box::pin(async move {
+      let (unwrap, rest) = T::Unwrap::forward_from(transport).await?;
+
+      Ok((
+        Wrap {
+          unwrap: Box::new(unwrap),
+        },
+        rest,
+      ))
+    })
+  }
+}
+
+impl<T> Describe for Wrap<T>
+where
+  T: DescribeWrapper,
+{
+  fn describe_in(ctx: &mut DescribeContext) -> ProtocolDesc
+  {
+    ctx.describe_wrapper::<T::Unwrap>(T::NAME)
+  }
+}
@@ -1,27 +1,86 @@
 pub mod prelude
 {
+  #[cfg(feature = "cbor")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::Cbor;
+  #[cfg(feature = "json")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::Json;
+  #[cfg(feature = "local-executor")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::LocalExecutor;
+  #[cfg(feature = "msgpack")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::MessagePack;
+  #[cfg(feature = "tokio-runtime")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::{
+    connect_shared_channel,
+    connect_shared_channel_with,
+    listen_shared_channel,
+    serve_shared_channel,
+    SocketListener,
+    SocketTransport,
+  };
+  #[cfg(feature = "simulation")]
+  #[doc(inline)]
+  pub use crate::internal::base::public::{
+    explore_seeds,
+    Simulation,
+  };
   #[doc(inline)]
   pub use crate::internal::{
     base::public::{
+      describe,
+      deserialize_shared_channel,
+      deserialize_shared_channel_with,
+      record_session,
+      replay_transcript,
+      serialize_shared_channel,
+      AcquireOptions,
       AppendContext,
+      Bincode,
+      ChannelError,
+      Compatibility,
       Context,
       ContextLens,
+      Describe,
+      DescribeValue,
       Empty,
       EmptyContext,
       ForwardChannel,
+      ForwardError,
+      Frame,
       HasRecApp,
+      InMemoryMetrics,
+      IpcTransport,
+      MetricsSnapshot,
+      MonitoredTransport,
       PartialSession,
+      Party,
+      PositiveProtocol,
       Protocol,
+      ProtocolDesc,
       Rec,
       RecApp,
       RecX,
+      Recorder,
       Release,
+      SerializedSharedChannel,
       Session,
+      SessionError,
       SharedChannel,
+      SharedMetrics,
       SharedProtocol,
       SharedRecApp,
       SharedSession,
       Slot,
+      Transcript,
+      TranscriptEntry,
+      TranscriptTransport,
+      Transport,
+      TransportFuture,
+      WireFormat,
     },
     functional::public::{
       absurd,
@@ -69,8 +128,10 @@ pub mod prelude
       Z,
     },
     protocol::public::{
+      DescribeWrapper,
       End,
       ExternalChoice,
+      ForwardWrapper,
       InternalChoice,
       LinearToShared,
       ReceiveChannel,
@@ -83,7 +144,10 @@ pub mod prelude
     },
     session::public::{
       accept_shared_session,
+      accept_shared_session_restartable,
       acquire_shared_session,
+      acquire_shared_session_or,
+      acquire_shared_session_with_timeout,
       append_emtpy_slot,
       apply_channel,
       async_acquire_shared_session,
@@ -98,6 +162,7 @@ pub mod prelude
       forward,
       include_session,
       join_sessions,
+      mock_peer,
       new_session,
       offer_case,
       offer_choice,
@@ -115,6 +180,8 @@ pub mod prelude
       run_session,
       run_session_with_result,
       run_shared_session,
+      run_shared_session_pool_with_config,
+      run_shared_session_with_config,
       run_shared_session_with_join_handle,
       send_channel_from,
       send_channel_to,
@@ -127,15 +194,23 @@ pub mod prelude
       terminate,
       terminate_async,
       terminate_nil,
+      timeout,
+      timeout_or_fail,
+      try_acquire_shared_session,
       unfix_session,
       unwrap_session,
       wait,
       wait_session,
       wait_sessions,
       wrap_session,
+      AcquireScheduling,
       AllLeft,
       AllRight,
       Cut,
+      MockPeer,
+      OverflowPolicy,
+      SharedSessionConfig,
+      SharedShutdown,
       L,
       R,
     },
@@ -1,10 +1,12 @@
 use async_macros::join;
-use tokio::task;
 
 use crate::internal::{
   base::{
     once_channel,
+    spawn_child,
+    unsafe_continue_session,
     unsafe_create_session,
+    unsafe_create_step_session,
     unsafe_run_session,
     AppendContext,
     Context,
@@ -12,6 +14,7 @@ use crate::internal::{
     Empty,
     PartialSession,
     Protocol,
+    SessionError,
   },
   functional::Nat,
   protocol::SendChannel,
@@ -27,7 +30,7 @@ where
   C: Context,
   N: ContextLens<C, A, Empty>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
+  unsafe_create_step_session(move |ctx1, sender1| async move {
     let (p_chan, ctx2) = N::extract_source(ctx1);
 
     let (sender2, receiver2) = once_channel();
@@ -36,21 +39,17 @@ where
 
     let ctx3 = N::insert_target((), ctx2);
 
-    let child1 = task::spawn(async move {
-      let p = p_chan.recv().await.unwrap();
+    sender1.send(SendChannel(receiver2, receiver3))?;
 
-      sender2.send(p).unwrap();
-    });
+    let step = unsafe_continue_session(cont, ctx3, sender3);
 
-    let child2 = task::spawn(async move {
-      sender1.send(SendChannel(receiver2, receiver3)).unwrap();
-    });
+    Ok(step.with_forward(async move {
+      let p = p_chan.recv().await?;
 
-    let child3 = task::spawn(async {
-      unsafe_run_session(cont, ctx3, sender3).await;
-    });
+      sender2.send(p)?;
 
-    let _ = join!(child1, child2, child3).await;
+      Ok(())
+    }))
   })
 }
 
@@ -68,10 +67,10 @@ where
 {
   let cont = cont_builder(C2::Length::nat());
 
-  unsafe_create_session(move |ctx1, sender1| async move {
+  unsafe_create_step_session(move |ctx1, sender1| async move {
     let (pair_chan, ctx2) = N::extract_source(ctx1);
 
-    let SendChannel(p_chan, y_chan) = pair_chan.recv().await.unwrap();
+    let SendChannel(p_chan, y_chan) = pair_chan.recv().await?;
 
     let ctx3 = N::insert_target(y_chan, ctx2);
 
@@ -80,7 +79,7 @@ where
       (p_chan, ()),
     );
 
-    unsafe_run_session(cont, ctx4, sender1).await;
+    Ok(unsafe_continue_session(cont, ctx4, sender1))
   })
 }
 
@@ -120,23 +119,25 @@ where
 
     // the first thread task::spawns immediately
 
-    let child1 = task::spawn(async move {
-      unsafe_run_session(cont1, ctx1, sender1).await;
-    });
+    let child1 = spawn_child(unsafe_run_session(cont1, ctx1, sender1));
 
     // the sender here blocks until the inner channel pairs
     // are received on the other side
-    let child2 = task::spawn(async move {
-      sender.send(SendChannel(receiver1, receiver2)).unwrap();
+    let child2 = spawn_child(async move {
+      sender.send(SendChannel(receiver1, receiver2))?;
+
+      Ok::<_, SessionError>(())
     });
 
     // the second thread is blocked until the first channel is being accessed
 
-    let child3 = task::spawn(async move {
-      unsafe_run_session(cont2, ctx2, sender2).await;
-    });
+    let child3 = spawn_child(unsafe_run_session(cont2, ctx2, sender2));
+
+    let (res1, res2, res3) = join!(child1, child2, child3).await;
 
-    let _ = join!(child1, child2, child3).await;
+    res1??;
+    res2??;
+    res3?
   })
 }
 
@@ -153,10 +154,10 @@ where
   SourceLens: ContextLens<I, SendChannel<P1, P2>, P2>,
   TargetLens: ContextLens<SourceLens::Target, Empty, P1>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
+  unsafe_create_step_session(move |ctx1, sender1| async move {
     let (pair_chan, ctx2) = SourceLens::extract_source(ctx1);
 
-    let SendChannel(p_chan, y_chan) = pair_chan.recv().await.unwrap();
+    let SendChannel(p_chan, y_chan) = pair_chan.recv().await?;
 
     let ctx3 = SourceLens::insert_target(y_chan, ctx2);
 
@@ -164,6 +165,6 @@ where
 
     let ctx5 = TargetLens::insert_target(p_chan, ctx4);
 
-    unsafe_run_session(cont, ctx5, sender1).await;
+    Ok(unsafe_continue_session(cont, ctx5, sender1))
   })
 }
@@ -1,8 +1,9 @@
 use crate::internal::{
   base::{
     once_channel,
-    unsafe_create_session,
-    unsafe_run_session,
+    trace_action,
+    unsafe_continue_session,
+    unsafe_create_step_session,
     Context,
     ContextLens,
     PartialSession,
@@ -35,30 +36,32 @@ where
   N: ContextLens<C1, ExternalChoice<Row1>, B, Target = C2>,
   M: Prism<Row2, Elem = B>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
-    let (receiver1, ctx2) = N::extract_source(ctx1);
+  unsafe_create_step_session(move |ctx1, sender1| {
+    trace_action("choose", Some(N::slot_index()), async move {
+      let (receiver1, ctx2) = N::extract_source(ctx1);
 
-    let choice: AppSum<Row2, ()> = M::inject_elem(wrap_type_app(()));
+      let choice: AppSum<Row2, ()> = M::inject_elem(wrap_type_app(()));
 
-    let ExternalChoice { sender: sender2 } = receiver1.recv().await.unwrap();
+      let ExternalChoice { sender: sender2 } = receiver1.recv().await?;
 
-    let (sender3, receiver3) = once_channel();
+      let (sender3, receiver3) = once_channel();
 
-    sender2.send((Value(choice), sender3)).unwrap();
+      sender2.send((Value(choice), sender3))?;
 
-    let receiver_sum = receiver3.recv().await.unwrap();
+      let receiver_sum = receiver3.recv().await?;
 
-    let m_receiver = M::extract_elem(receiver_sum);
+      let m_receiver = M::extract_elem(receiver_sum);
 
-    match m_receiver {
-      Some(receiver4) => {
-        let ctx3 = N::insert_target(receiver4.get_applied(), ctx2);
+      match m_receiver {
+        Some(receiver4) => {
+          let ctx3 = N::insert_target(receiver4.get_applied(), ctx2);
 
-        unsafe_run_session(cont, ctx3, sender1).await;
+          Ok(unsafe_continue_session(cont, ctx3, sender1))
+        }
+        None => {
+          panic!("impossible happened: received mismatch choice from provider");
+        }
       }
-      None => {
-        panic!("impossible happened: received mismatch choice from provider");
-      }
-    }
+    })
   })
 }
@@ -9,7 +9,8 @@ use super::{
 use crate::internal::{
   base::{
     once_channel,
-    unsafe_create_session,
+    trace_action,
+    unsafe_create_step_session,
     Context,
     PartialSession,
     Value,
@@ -46,21 +47,23 @@ where
   SessionSum: Send + 'static,
   InjectSessionSum: Send + 'static,
 {
-  unsafe_create_session(move |ctx, sender1| async move {
-    let (sender2, receiver2) = once_channel();
+  unsafe_create_step_session(move |ctx, sender1| {
+    trace_action("offer_choice", None, async move {
+      let (sender2, receiver2) = once_channel();
 
-    let payload = ExternalChoice::<Row1> { sender: sender2 };
+      let payload = ExternalChoice::<Row1> { sender: sender2 };
 
-    sender1.send(payload).unwrap();
+      sender1.send(payload)?;
 
-    let (Value(choice), sender3) = receiver2.recv().await.unwrap();
+      let (Value(choice), sender3) = receiver2.recv().await?;
 
-    let cont3 = selector_to_inject_session(choice);
+      let cont3 = selector_to_inject_session(choice);
 
-    let cont4 = Row2::flatten_sum(cont3);
+      let cont4 = Row2::flatten_sum(cont3);
 
-    let cont5 = wrap_sum_app(cont1(cont4));
+      let cont5 = wrap_sum_app(cont1(cont4));
 
-    run_choice_cont(ctx, sender3, cont5).await;
+      run_choice_cont(ctx, sender3, cont5)
+    })
   })
 }
@@ -1,8 +1,4 @@
-use std::{
-  future::Future,
-  marker::PhantomData,
-  pin::Pin,
-};
+use std::marker::PhantomData;
 
 use super::super::cloak_session::*;
 use crate::internal::{
@@ -10,11 +6,12 @@ use crate::internal::{
   functional::*,
 };
 
-pub async fn run_choice_cont<Row, C>(
+pub fn run_choice_cont<Row, C>(
   ctx: C::Endpoints,
   sender: SenderOnce<AppSum<Row, ReceiverF>>,
   cont1: AppSum<Row, SessionF<C>>,
-) where
+) -> Result<SessionStep, SessionError>
+where
   C: Context,
   Row: ElimSum,
   Row: SplitRow,
@@ -24,9 +21,9 @@ pub async fn run_choice_cont<Row, C>(
 
   let (receiver_sum, cont6) = Row::split_row(res);
 
-  sender.send(receiver_sum).unwrap();
+  sender.send(receiver_sum)?;
 
-  Row::elim_sum(ElimConst {}, cont6).await;
+  Ok(Row::elim_sum(ElimConst {}, cont6))
 }
 
 struct RunSession<C>
@@ -44,36 +41,24 @@ where
   phantom: PhantomData<A>,
 }
 
-impl<C, A>
-  NeedPartialSession<
-    C,
-    A,
-    (
-      ReceiverOnce<A>,
-      Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
-    ),
-  > for SessionRunner<C, A>
+impl<C, A> NeedPartialSession<C, A, (ReceiverOnce<A>, SessionStep)>
+  for SessionRunner<C, A>
 where
   C: Context,
 {
   fn on_partial_session(
     self: Box<Self>,
     cont: PartialSession<C, A>,
-  ) -> (
-    ReceiverOnce<A>,
-    Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
-  )
+  ) -> (ReceiverOnce<A>, SessionStep)
   where
     C: Context,
     A: Protocol,
   {
     let (sender, receiver) = once_channel();
 
-    let future = Box::pin(async move {
-      unsafe_run_session(cont, self.ctx, sender).await;
-    });
+    let step = unsafe_continue_session(cont, self.ctx, sender);
 
-    (receiver, future)
+    (receiver, step)
   }
 }
 
@@ -81,8 +66,7 @@ impl<Root, C> InjectLift<Root> for RunSession<C>
 where
   C: Context,
 {
-  type InjectF =
-    Merge<ReceiverF, Const<Pin<Box<dyn Future<Output = ()> + Send + 'static>>>>;
+  type InjectF = Merge<ReceiverF, Const<SessionStep>>;
   type SourceF = SessionF<C>;
   type TargetF = ();
 
@@ -101,8 +85,8 @@ where
       phantom: PhantomData,
     };
 
-    let (receiver, future) = *with_session(cont2, Box::new(runner));
+    let (receiver, step) = *with_session(cont2, Box::new(runner));
 
-    wrap_type_app((wrap_type_app(receiver), wrap_type_app(future)))
+    wrap_type_app((wrap_type_app(receiver), wrap_type_app(step)))
   }
 }
@@ -5,7 +5,8 @@ use super::{
 };
 use crate::internal::{
   base::{
-    unsafe_create_session,
+    trace_action,
+    unsafe_create_step_session,
     Context,
     ContextLens,
     Empty,
@@ -52,30 +53,30 @@ where
   SessionSum: Send + 'static,
   InjectSessionSum: Send + 'static,
 {
-  unsafe_create_session(move |ctx1, sender| async move {
-    let (sum_chan, ctx2) = N::extract_source(ctx1);
+  unsafe_create_step_session(move |ctx1, sender| {
+    trace_action("case", Some(N::slot_index()), async move {
+      let (sum_chan, ctx2) = N::extract_source(ctx1);
 
-    let InternalChoice {
-      field: receiver_sum1,
-    } = sum_chan.recv().await.unwrap();
+      let InternalChoice {
+        field: receiver_sum1,
+      } = sum_chan.recv().await?;
 
-    let (receiver_sum2, selector_sum) = receiver_to_selector(receiver_sum1);
+      let (receiver_sum2, selector_sum) = receiver_to_selector(receiver_sum1);
 
-    let cont3 = lift_unit_to_session(selector_sum);
+      let cont3 = lift_unit_to_session(selector_sum);
 
-    let cont3a = Row2::flatten_sum(cont3);
+      let cont3a = Row2::flatten_sum(cont3);
 
-    let cont4 = wrap_sum_app(cont1(cont3a));
+      let cont4 = wrap_sum_app(cont1(cont3a));
 
-    let cont5 = Row2::intersect_sum(receiver_sum2, cont4);
+      let cont5 = Row2::intersect_sum(receiver_sum2, cont4);
 
-    match cont5 {
-      Some(cont6) => {
-        run_case_cont(ctx2, sender, cont6).await;
+      match cont5 {
+        Some(cont6) => Ok(run_case_cont(ctx2, sender, cont6)),
+        None => {
+          panic!("impossible happened: received mismatch choice continuation");
+        }
       }
-      None => {
-        panic!("impossible happened: received mismatch choice continuation");
-      }
-    }
+    })
   })
 }
@@ -1,11 +1,9 @@
-use async_macros::join;
-use tokio::task;
-
 use crate::internal::{
   base::{
     once_channel,
-    unsafe_create_session,
-    unsafe_run_session,
+    trace_action,
+    unsafe_continue_session,
+    unsafe_create_step_session,
     Context,
     PartialSession,
     Protocol,
@@ -32,21 +30,15 @@ where
   Row2: SumApp<ReceiverF>,
   N: Prism<Row2, Elem = A>,
 {
-  unsafe_create_session(move |ctx, sender1| async move {
-    let (sender2, receiver2) = once_channel();
-
-    let child1 = task::spawn(async move {
-      unsafe_run_session(cont, ctx, sender2).await;
-    });
+  unsafe_create_step_session(move |ctx, sender1| {
+    trace_action("offer_case", None, async move {
+      let (sender2, receiver2) = once_channel();
 
-    let child2 = task::spawn(async move {
-      sender1
-        .send(InternalChoice {
-          field: N::inject_elem(wrap_type_app(receiver2)),
-        })
-        .unwrap();
-    });
+      sender1.send(InternalChoice {
+        field: N::inject_elem(wrap_type_app(receiver2)),
+      })?;
 
-    let _ = join!(child1, child2).await;
+      Ok(unsafe_continue_session(cont, ctx, sender2))
+    })
   })
 }
@@ -1,8 +1,4 @@
-use std::{
-  future::Future,
-  marker::PhantomData,
-  pin::Pin,
-};
+use std::marker::PhantomData;
 
 use super::super::internal_session::*;
 use crate::internal::{
@@ -11,11 +7,12 @@ use crate::internal::{
   protocol::*,
 };
 
-pub async fn run_case_cont<N, C, D, B, Row1, Row2>(
+pub fn run_case_cont<N, C, D, B, Row1, Row2>(
   ctx: D::Endpoints,
   sender: SenderOnce<B>,
   cont1: AppSum<Row2, Merge<ReceiverF, InternalSessionF<N, C, B, Row1, D>>>,
-) where
+) -> SessionStep
+where
   C: Context,
   D: Context,
   B: Protocol,
@@ -30,7 +27,7 @@ pub async fn run_case_cont<N, C, D, B, Row1, Row2>(
     phantom: PhantomData,
   };
 
-  Row2::elim_sum(cont2, cont1).await;
+  Row2::elim_sum(cont2, cont1)
 }
 
 struct ContRunner1<N, C, B, Row, D>
@@ -65,16 +62,8 @@ where
   phantom: PhantomData<(N, C, Row)>,
 }
 
-impl<N, C, A, B, Row, D>
-  NeedInternalSession<
-    N,
-    C,
-    A,
-    B,
-    Row,
-    D,
-    Pin<Box<dyn Future<Output = ()> + Send>>,
-  > for ContRunner2<N, C, A, B, Row, D>
+impl<N, C, A, B, Row, D> NeedInternalSession<N, C, A, B, Row, D, SessionStep>
+  for ContRunner2<N, C, A, B, Row, D>
 where
   B: Protocol,
   C: Context,
@@ -86,7 +75,7 @@ where
   fn on_internal_session(
     self: Box<Self>,
     cont: InternalSession<N, C, A, B, Row, D>,
-  ) -> Pin<Box<dyn Future<Output = ()> + Send>>
+  ) -> SessionStep
   where
     A: Protocol,
     B: Protocol,
@@ -103,17 +92,13 @@ where
       receiver, ctx1,
     );
 
-    Box::pin(async move {
-      unsafe_run_session(cont.session, ctx2, sender).await;
-    })
+    unsafe_continue_session(cont.session, ctx2, sender)
   }
 }
 
 impl<B, N, C, Row, D>
-  ElimField<
-    Merge<ReceiverF, InternalSessionF<N, C, B, Row, D>>,
-    Pin<Box<dyn Future<Output = ()> + Send>>,
-  > for ContRunner1<N, C, B, Row, D>
+  ElimField<Merge<ReceiverF, InternalSessionF<N, C, B, Row, D>>, SessionStep>
+  for ContRunner1<N, C, B, Row, D>
 where
   B: Protocol,
   C: Context,
@@ -125,7 +110,7 @@ where
   fn elim_field<A>(
     self,
     fa: App<Merge<ReceiverF, InternalSessionF<N, C, B, Row, D>>, A>,
-  ) -> Pin<Box<dyn Future<Output = ()> + Send>>
+  ) -> SessionStep
   where
     A: Send + 'static,
   {
@@ -1,7 +1,7 @@
 use crate::internal::{
   base::{
-    unsafe_create_session,
-    unsafe_run_session,
+    unsafe_continue_session,
+    unsafe_create_step_session,
     AppendContext,
     Empty,
     EmptyContext,
@@ -29,10 +29,10 @@ where
   C: EmptyContext,
   A: Protocol,
 {
-  unsafe_create_session(move |(), sender| async move {
+  unsafe_create_step_session(move |(), sender| async move {
     let ctx = <C as EmptyContext>::empty_values();
 
-    unsafe_run_session(cont, ctx, sender).await;
+    Ok(unsafe_continue_session(cont, ctx, sender))
   })
 }
 
@@ -41,8 +41,8 @@ where
   C: EmptyContext,
   A: Protocol,
 {
-  unsafe_create_session(move |_, sender| async move {
-    unsafe_run_session(cont, (), sender).await
+  unsafe_create_step_session(move |_, sender| async move {
+    Ok(unsafe_continue_session(cont, (), sender))
   })
 }
 
@@ -53,9 +53,9 @@ where
   A: Protocol,
   C: AppendContext<(Empty, ())>,
 {
-  unsafe_create_session(move |ctx1, sender| async move {
+  unsafe_create_step_session(move |ctx1, sender| async move {
     let (ctx2, _) = C::split_context(ctx1);
-    unsafe_run_session(cont, ctx2, sender).await
+    Ok(unsafe_continue_session(cont, ctx2, sender))
   })
 }
 
@@ -1,9 +1,9 @@
 use async_macros::join;
-use tokio::task;
 
 use crate::internal::{
   base::{
     once_channel,
+    spawn_child,
     unsafe_create_session,
     unsafe_run_session,
     AppendContext,
@@ -204,15 +204,14 @@ where
 
     let ctx3 = C2::append_context(ctx2, (receiver2, ()));
 
-    let child1 = task::spawn(async move {
-      unsafe_run_session(cont3, ctx3, sender1).await;
-    });
+    let child1 = spawn_child(unsafe_run_session(cont3, ctx3, sender1));
 
-    let child2 = task::spawn(async {
-      unsafe_run_session(cont1, ctx1, sender2).await;
-    });
+    let child2 = spawn_child(unsafe_run_session(cont1, ctx1, sender2));
 
-    let _ = join!(child1, child2).await;
+    let (res1, res2) = join!(child1, child2).await;
+
+    res1??;
+    res2?
   })
 }
 
@@ -246,14 +245,13 @@ where
     let ctx4 =
       <C1 as AppendContext<(A, ())>>::append_context(ctx2, (a_receiver, ()));
 
-    let child1 = task::spawn(async {
-      unsafe_run_session(cont1, ctx4, b_sender).await;
-    });
+    let child1 = spawn_child(unsafe_run_session(cont1, ctx4, b_sender));
+
+    let child2 = spawn_child(unsafe_run_session(cont2, ctx3, a_sender));
 
-    let child2 = task::spawn(async {
-      unsafe_run_session(cont2, ctx3, a_sender).await;
-    });
+    let (res1, res2) = join!(child1, child2).await;
 
-    let _ = join!(child1, child2).await;
+    res1??;
+    res2?
   })
 }
@@ -2,8 +2,9 @@ use std::future::Future;
 
 use crate::internal::{
   base::{
+    unsafe_continue_session,
     unsafe_create_session,
-    unsafe_run_session,
+    unsafe_create_step_session,
     Context,
     ContextLens,
     Empty,
@@ -33,7 +34,9 @@ where
   unsafe_create_session(move |_, sender| async move {
     cleaner().await;
 
-    sender.send(End()).unwrap();
+    sender.send(End())?;
+
+    Ok(())
   })
 }
 
@@ -66,13 +69,13 @@ where
   A: Protocol,
   N: ContextLens<C, End, Empty>,
 {
-  unsafe_create_session(move |ctx1, sender| async move {
+  unsafe_create_step_session(move |ctx1, sender| async move {
     let (receiver, ctx2) = N::extract_source(ctx1);
 
     let ctx3 = N::insert_target((), ctx2);
 
-    receiver.recv().await.unwrap();
+    receiver.recv().await?;
 
-    unsafe_run_session(cont, ctx3, sender).await;
+    Ok(unsafe_continue_session(cont, ctx3, sender))
   })
 }
@@ -1,6 +1,3 @@
-use async_macros::join;
-use tokio::task;
-
 use crate::internal::base::*;
 
 pub fn fix_session<R, F, A, C>(
@@ -13,18 +10,18 @@ where
   A: Protocol,
   F: RecApp<(RecX<R, F>, R), Applied = A>,
 {
-  unsafe_create_session(move |ctx, sender1| async move {
+  unsafe_create_step_session(move |ctx, sender1| async move {
     let (sender2, receiver): (SenderOnce<A>, _) = once_channel();
 
-    let child1 = task::spawn(async move {
-      let val = receiver.recv().await.unwrap();
+    let step = unsafe_continue_session(cont, ctx, sender2);
 
-      sender1.send(fix(val)).unwrap();
-    });
+    Ok(step.with_forward(async move {
+      let val = receiver.recv().await?;
 
-    let child2 = task::spawn(unsafe_run_session(cont, ctx, sender2));
+      sender1.send(fix(val))?;
 
-    let _ = join!(child1, child2).await;
+      Ok(())
+    }))
   })
 }
 
@@ -41,21 +38,21 @@ where
   A: Protocol,
   N: ContextLens<C, RecX<R, F>, A>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
+  unsafe_create_step_session(move |ctx1, sender1| async move {
     let (receiver1, ctx2) = N::extract_source(ctx1);
 
     let (sender2, receiver2) = once_channel();
 
     let ctx3 = N::insert_target(receiver2, ctx2);
 
-    let child1 = task::spawn(async move {
-      let val = receiver1.recv().await.unwrap();
+    let step = unsafe_continue_session(cont, ctx3, sender1);
 
-      sender2.send(unfix(val)).unwrap();
-    });
+    Ok(step.with_forward(async move {
+      let val = receiver1.recv().await?;
 
-    let child2 = task::spawn(unsafe_run_session(cont, ctx3, sender1));
+      sender2.send(unfix(val))?;
 
-    let _ = join!(child1, child2).await;
+      Ok(())
+    }))
   })
 }
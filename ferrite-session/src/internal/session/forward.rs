@@ -18,8 +18,10 @@ where
   unsafe_create_session(move |ctx, sender| async move {
     let (receiver, _) = N::extract_source(ctx);
 
-    let val = receiver.recv().await.unwrap();
+    let val = receiver.recv().await?;
 
-    sender.send(val).unwrap();
+    sender.send(val)?;
+
+    Ok(())
   })
 }
@@ -0,0 +1,230 @@
+use std::sync::Mutex;
+
+use crate::internal::{
+  base::{
+    once_channel,
+    spawn_child,
+    unsafe_run_session,
+    ChildTask,
+    Protocol,
+    ReceiverF,
+    ReceiverOnce,
+    Session,
+    SessionError,
+    Value,
+  },
+  functional::{
+    wrap_type_app,
+    App,
+    AppSum,
+    FlattenSumApp,
+    NaturalTransformation,
+    Prism,
+    RowCon,
+    SumFunctor,
+    ToRow,
+    TyCon,
+    TypeApp,
+  },
+  protocol::{
+    End,
+    ExternalChoice,
+    InternalChoice,
+    ReceiveValue,
+    SendValue,
+  },
+};
+
+/*
+   A scripted client for the session under test. Every call consumes the
+   peer and gives back a peer for the rest of the protocol, so a script
+   that does not follow the protocol fails to type check:
+
+     let peer = mock_peer(session).send("Alice".to_string()).await?;
+     let (count, peer) = peer.recv().await?;
+     peer.wait_end().await?;
+
+   The session runs in its own task for as long as the peer is alive,
+   and is aborted if the peer is dropped before the protocol ends.
+*/
+
+pub struct MockPeer<A>
+{
+  receiver: ReceiverOnce<A>,
+  task: ChildTask<Result<(), SessionError>>,
+}
+
+pub struct MockPeerF {}
+
+struct LiftPeer
+{
+  task: Mutex<Option<ChildTask<Result<(), SessionError>>>>,
+}
+
+pub fn mock_peer<A>(session: Session<A>) -> MockPeer<A>
+where
+  A: Protocol,
+{
+  let (sender, receiver) = once_channel();
+
+  let task = spawn_child(unsafe_run_session(session, (), sender));
+
+  MockPeer { receiver, task }
+}
+
+impl<A> MockPeer<A>
+{
+  // The channel is only closed early when the session gives up on it,
+  // in which case the error the session itself ended with says more
+  // than the closed channel does.
+  async fn receive(
+    self
+  ) -> Result<(A, ChildTask<Result<(), SessionError>>), SessionError>
+  {
+    let MockPeer { receiver, task } = self;
+
+    match receiver.recv().await {
+      Ok(payload) => Ok((payload, task)),
+      Err(err) => {
+        task.await??;
+
+        Err(err.into())
+      }
+    }
+  }
+}
+
+impl<T, A> MockPeer<ReceiveValue<T, A>>
+where
+  T: Send + 'static,
+  A: Protocol,
+{
+  pub async fn send(
+    self,
+    val: T,
+  ) -> Result<MockPeer<A>, SessionError>
+  {
+    let (ReceiveValue(sender1), task) = self.receive().await?;
+
+    let (sender2, receiver) = once_channel();
+
+    sender1.send((Value(val), sender2))?;
+
+    Ok(MockPeer { receiver, task })
+  }
+}
+
+impl<T, A> MockPeer<SendValue<T, A>>
+where
+  T: Send + 'static,
+  A: Protocol,
+{
+  pub async fn recv(self) -> Result<(T, MockPeer<A>), SessionError>
+  {
+    let (SendValue((Value(val), receiver)), task) = self.receive().await?;
+
+    Ok((val, MockPeer { receiver, task }))
+  }
+}
+
+impl<Row1, Row2> MockPeer<ExternalChoice<Row1>>
+where
+  Row1: Send + 'static,
+  Row1: ToRow<Row = Row2>,
+  Row2: RowCon,
+{
+  pub async fn choose<M, B>(
+    self,
+    _: M,
+  ) -> Result<MockPeer<B>, SessionError>
+  where
+    B: Protocol,
+    M: Prism<Row2, Elem = B>,
+  {
+    let (ExternalChoice { sender: sender1 }, task) = self.receive().await?;
+
+    let choice: AppSum<Row2, ()> = M::inject_elem(wrap_type_app(()));
+
+    let (sender2, receiver2) = once_channel();
+
+    sender1.send((Value(choice), sender2))?;
+
+    let receiver_sum = receiver2.recv().await?;
+
+    match M::extract_elem(receiver_sum) {
+      Some(receiver) => Ok(MockPeer {
+        receiver: receiver.get_applied(),
+        task,
+      }),
+      None => {
+        panic!("impossible happened: received mismatch choice from provider");
+      }
+    }
+  }
+}
+
+impl<Row1, Row2> MockPeer<InternalChoice<Row1>>
+where
+  Row1: Send + 'static,
+  Row1: ToRow<Row = Row2>,
+  Row2: SumFunctor,
+  Row2: FlattenSumApp<MockPeerF>,
+{
+  // The label picked by the session, with the peer for that branch.
+  // For a choice defined with define_choice!, the result converts into
+  // its choice enum, e.g. EitherChoice for Either.
+  pub async fn offer(self) -> Result<Row2::FlattenApplied, SessionError>
+  {
+    let (InternalChoice { field }, task) = self.receive().await?;
+
+    let lift = LiftPeer {
+      task: Mutex::new(Some(task)),
+    };
+
+    let peer_sum: AppSum<Row2, MockPeerF> = Row2::lift_sum(&lift, field);
+
+    Ok(Row2::flatten_sum(peer_sum))
+  }
+}
+
+impl MockPeer<End>
+{
+  // Wait for the session to terminate, and for its task to finish
+  // successfully.
+  pub async fn wait_end(self) -> Result<(), SessionError>
+  {
+    let (End(), task) = self.receive().await?;
+
+    task.await?
+  }
+}
+
+impl TyCon for MockPeerF {}
+
+impl<A> TypeApp<A> for MockPeerF
+where
+  A: Send + 'static,
+{
+  type Applied = MockPeer<A>;
+}
+
+impl NaturalTransformation<ReceiverF, MockPeerF> for LiftPeer
+{
+  fn lift<A>(
+    &self,
+    receiver: App<ReceiverF, A>,
+  ) -> App<MockPeerF, A>
+  where
+    A: Send + 'static,
+  {
+    // Only the chosen branch is ever lifted.
+    let task = self.task.lock().unwrap().take().unwrap_or_else(|| {
+      panic!("impossible happened: lifted more than one choice branch")
+    });
+
+    wrap_type_app(MockPeer {
+      receiver: receiver.get_applied(),
+      task,
+    })
+  }
+}
@@ -9,9 +9,11 @@ mod end;
 mod fix;
 mod forward;
 mod include;
+mod mock;
 mod run;
 mod shared;
 mod step;
+mod timeout;
 mod value;
 mod wrap;
 
@@ -73,21 +75,40 @@ pub use self::{
     wait_session,
     wait_sessions,
   },
+  mock::{
+    mock_peer,
+    MockPeer,
+    MockPeerF,
+  },
   run::{
     run_session,
     run_session_with_result,
     run_shared_session,
+    run_shared_session_pool_with_config,
+    run_shared_session_with_config,
     run_shared_session_with_join_handle,
+    AcquireScheduling,
+    OverflowPolicy,
+    SharedSessionConfig,
+    SharedShutdown,
   },
   shared::{
     accept_shared_session,
+    accept_shared_session_restartable,
     acquire_shared_session,
+    acquire_shared_session_or,
+    acquire_shared_session_with_timeout,
     async_acquire_shared_session,
     async_acquire_shared_session_with_result,
     detach_shared_session,
     release_shared_session,
+    try_acquire_shared_session,
   },
   step::step,
+  timeout::{
+    timeout,
+    timeout_or_fail,
+  },
   value::{
     receive_value,
     receive_value_from,
@@ -1,7 +1,10 @@
 #[doc(inline)]
 pub use super::{
   accept_shared_session,
+  accept_shared_session_restartable,
   acquire_shared_session,
+  acquire_shared_session_or,
+  acquire_shared_session_with_timeout,
   append_emtpy_slot,
   apply_channel,
   async_acquire_shared_session,
@@ -16,6 +19,7 @@ pub use super::{
   forward,
   include_session,
   join_sessions,
+  mock_peer,
   new_session,
   offer_case,
   offer_choice,
@@ -33,6 +37,8 @@ pub use super::{
   run_session,
   run_session_with_result,
   run_shared_session,
+  run_shared_session_pool_with_config,
+  run_shared_session_with_config,
   run_shared_session_with_join_handle,
   send_channel_from,
   send_channel_to,
@@ -45,15 +51,23 @@ pub use super::{
   terminate,
   terminate_async,
   terminate_nil,
+  timeout,
+  timeout_or_fail,
+  try_acquire_shared_session,
   unfix_session,
   unwrap_session,
   wait,
   wait_session,
   wait_sessions,
   wrap_session,
+  AcquireScheduling,
   AllLeft,
   AllRight,
   Cut,
+  MockPeer,
+  OverflowPolicy,
+  SharedSessionConfig,
+  SharedShutdown,
   L,
   R,
 };
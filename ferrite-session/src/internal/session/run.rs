@@ -1,15 +1,48 @@
-use async_macros::join;
-use tokio::task;
+use std::{
+  collections::{
+    HashMap,
+    VecDeque,
+  },
+  iter,
+  pin::pin,
+  sync::Arc,
+  time::{
+    Duration,
+    Instant,
+  },
+};
+
+use futures::{
+  future::{
+    pending,
+    FutureExt,
+  },
+  select_biased,
+};
 
 use crate::internal::{
   base::{
     once_channel,
+    runtime::{
+      self,
+      JoinHandle,
+    },
+    spawn_child,
     unbounded,
     unsafe_create_shared_channel,
     unsafe_run_session,
     unsafe_run_shared_session,
+    AcquireMode,
+    AcquireOptions,
+    AcquireReceiver,
+    AcquireRequest,
+    ReceiverOnce,
+    Sender,
+    SenderOnce,
     Session,
+    SessionError,
     SharedChannel,
+    SharedMetrics,
     SharedProtocol,
     SharedSession,
     Value,
@@ -20,40 +53,97 @@ use crate::internal::{
   },
 };
 
-pub async fn run_session(session: Session<End>)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy
+{
+  Wait,
+  Reject,
+  ShedOldest,
+}
+
+// The order in which waiting acquirers are given the lock. Priority
+// serves higher priorities first, and otherwise keeps arrival order.
+// Fair also serves higher priorities first, but among equal ones
+// prefers the client that was served least recently, so that one
+// client acquiring in a loop cannot crowd out the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AcquireScheduling
+{
+  Fifo,
+  Priority,
+  Fair,
+}
+
+#[derive(Clone, Debug)]
+pub struct SharedSessionConfig
+{
+  pub capacity: usize,
+  pub overflow: OverflowPolicy,
+  pub scheduling: AcquireScheduling,
+  pub metrics: Option<Arc<dyn SharedMetrics>>,
+}
+
+pub struct SharedShutdown
+{
+  signal: SenderOnce<()>,
+  dispatcher: JoinHandle<()>,
+  providers: Vec<JoinHandle<Result<(), SessionError>>>,
+}
+
+impl Default for SharedSessionConfig
+{
+  fn default() -> Self
+  {
+    SharedSessionConfig {
+      capacity: usize::MAX,
+      overflow: OverflowPolicy::Wait,
+      scheduling: AcquireScheduling::Fifo,
+      metrics: None,
+    }
+  }
+}
+
+pub async fn run_session(session: Session<End>) -> Result<(), SessionError>
 {
   let (sender, receiver) = once_channel();
 
-  let child1 = task::spawn(async move {
-    unsafe_run_session(session, (), sender).await;
-  });
+  // The session runs in a task of its own, so that a panic in it is
+  // reported as an error instead of unwinding through the caller.
+  let child = spawn_child(unsafe_run_session(session, (), sender));
+
+  let received = receiver.recv().await;
 
-  let child2 = task::spawn(async move {
-    receiver.recv().await.unwrap();
-  });
+  child.await??;
 
-  let _ = join!(child1, child2).await;
+  received?;
+
+  Ok(())
 }
 
 pub async fn run_session_with_result<T>(
   session: Session<SendValue<T, End>>
-) -> T
+) -> Result<T, SessionError>
 where
   T: Send + 'static,
 {
   let (sender, receiver1) = once_channel();
 
-  let child1 = task::spawn(async move {
-    unsafe_run_session(session, (), sender).await;
-  });
+  let child1 = spawn_child(unsafe_run_session(session, (), sender));
+
+  let received = async move {
+    let SendValue((Value(val), receiver2)) = receiver1.recv().await?;
 
-  let SendValue((Value(val), receiver2)) = receiver1.recv().await.unwrap();
+    receiver2.recv().await?;
 
-  receiver2.recv().await.unwrap();
+    Ok::<_, SessionError>(val)
+  }
+  .await;
 
-  let _ = child1.await;
+  // Prefer the provider's own error, which tells whether it panicked,
+  // over the dropped endpoint observed on the receiving side.
+  child1.await??;
 
-  val
+  received
 }
 
 pub fn run_shared_session<A>(session: SharedSession<A>) -> SharedChannel<A>
@@ -67,40 +157,664 @@ where
 
 pub fn run_shared_session_with_join_handle<A>(
   session: SharedSession<A>
-) -> (SharedChannel<A>, task::JoinHandle<()>)
+) -> (SharedChannel<A>, JoinHandle<()>)
 where
   A: SharedProtocol,
 {
-  let (sender1, receiver1) = unbounded();
+  let (chan, shutdown) =
+    run_shared_session_with_config(session, SharedSessionConfig::default());
+
+  (chan, runtime::spawn(shutdown.stopped()))
+}
+
+/*
+   Run a shared session that keeps at most config.capacity acquire
+   requests waiting for the lock. Requests beyond that either wait to
+   be queued, are refused, or push out the oldest waiting request,
+   according to config.overflow. Refused acquirers run their fallback
+   continuation, or fail with SessionError::AcquireRefused.
+*/
+
+pub fn run_shared_session_with_config<A>(
+  session: SharedSession<A>,
+  config: SharedSessionConfig,
+) -> (SharedChannel<A>, SharedShutdown)
+where
+  A: SharedProtocol,
+{
+  spawn_shared_sessions(session, Vec::new(), config)
+}
+
+/*
+   Run a pool of replicas of a shared session behind a single shared
+   channel. Each acquire is passed on to the next idle replica in
+   round-robin order, and only waits in the queue when every replica
+   is holding a lock. This suits stateless shared services, where it
+   does not matter which replica serves a client. A pool always has
+   its first replica, followed by any number of others.
+*/
+
+pub fn run_shared_session_pool_with_config<A>(
+  (session, replicas): (SharedSession<A>, Vec<SharedSession<A>>),
+  config: SharedSessionConfig,
+) -> (SharedChannel<A>, SharedShutdown)
+where
+  A: SharedProtocol,
+{
+  spawn_shared_sessions(session, replicas, config)
+}
+
+fn spawn_shared_sessions<A>(
+  session: SharedSession<A>,
+  replicas: Vec<SharedSession<A>>,
+  config: SharedSessionConfig,
+) -> (SharedChannel<A>, SharedShutdown)
+where
+  A: SharedProtocol,
+{
+  let sessions: Vec<_> = iter::once(session).chain(replicas).collect();
 
   let (session2, receiver2) = unsafe_create_shared_channel();
 
-  task::spawn(async move {
-    info!("[run_shared_session] exec_shared_session");
+  let (signal, receiver3) = once_channel();
+
+  let mut senders = Vec::with_capacity(sessions.len());
+
+  let mut providers = Vec::with_capacity(sessions.len());
+
+  for session in sessions {
+    let (sender1, receiver1) = unbounded();
+
+    senders.push(sender1);
+
+    providers.push(runtime::spawn(async move {
+      info!("[run_shared_session] exec_shared_session");
+
+      let res = unsafe_run_shared_session(session, receiver1).await;
+
+      match &res {
+        Ok(()) => {
+          info!("[run_shared_session] exec_shared_session returned");
+        }
+        Err(err) => {
+          error!("[run_shared_session] exec_shared_session failed: {}", err);
+        }
+      }
+
+      res
+    }));
+  }
+
+  let dispatcher =
+    runtime::spawn(dispatch_acquires(receiver2, senders, receiver3, config));
 
-    unsafe_run_shared_session(session, receiver1).await;
+  (
+    session2,
+    SharedShutdown {
+      signal,
+      dispatcher,
+      providers,
+    },
+  )
+}
+
+struct Relay
+{
+  grant: ReceiverOnce<bool>,
+  granted: SenderOnce<bool>,
+  claim: ReceiverOnce<()>,
+  claimed: SenderOnce<()>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Replica
+{
+  Idle,
+  Busy,
+  Stopped,
+}
+
+// What a replica's probe task reports back to the dispatcher.
+enum ReplicaEvent
+{
+  Acquired(usize),
+  Idle(usize, bool),
+}
+
+// What the dispatcher wakes up to.
+enum DispatchEvent<A>
+{
+  Request(Option<AcquireRequest<A>>),
+  Shutdown(bool),
+  Replica(Option<ReplicaEvent>),
+}
+
+// Acquire requests wait in our own queue, and are passed on to an idle
+// provider one at a time. Each is followed by a probe: a request whose
+// claim is already dropped. The provider only grants it once it is
+// accepting again, and then skips it as an acquirer that gave up, so
+// the grant tells us when to pass on the next one. A probe that is
+// dropped instead means the provider has terminated. Try-acquires are
+// turned down unless some provider is idle with no one else waiting.
+async fn dispatch_acquires<A>(
+  requests: AcquireReceiver<A>,
+  providers: Vec<Sender<AcquireRequest<A>>>,
+  shutdown: ReceiverOnce<()>,
+  config: SharedSessionConfig,
+) where
+  A: SharedProtocol,
+{
+  let (idle_sender, idle_receiver) = unbounded::<ReplicaEvent>();
 
-    info!("[run_shared_session] exec_shared_session returned");
-  });
+  let mut probe_senders = Vec::with_capacity(providers.len());
 
-  let handle = task::spawn(async move {
-    loop {
-      let m_senders = receiver2.recv().await;
+  for i in 0..providers.len() {
+    let (probe_sender, probe_receiver) =
+      unbounded::<(Option<Relay>, ReceiverOnce<bool>)>();
 
-      debug!("[run_shared_session] received sender3");
+    let idle_sender = idle_sender.clone();
 
-      match m_senders {
-        Some(senders) => {
-          sender1.send(senders).unwrap();
+    // The grant and claim of a dispatched request are relayed through
+    // here, so that the lock only counts as acquired once the acquirer
+    // has claimed it, and always before the replica reports being idle
+    // again.
+    runtime::spawn(async move {
+      while let Some((m_relay, probe)) = probe_receiver.recv().await {
+        if let Some(relay) = m_relay {
+          if relay.run().await
+            && idle_sender.send(ReplicaEvent::Acquired(i)).is_err()
+          {
+            return;
+          }
         }
-        None => {
-          info!("[run_shared_session] terminating shared session");
 
+        let alive = probe.recv().await.is_ok();
+
+        if idle_sender.send(ReplicaEvent::Idle(i, alive)).is_err() || !alive {
           return;
         }
       }
+    });
+
+    probe_senders.push(probe_sender);
+  }
+
+  drop(idle_sender);
+
+  let send_request = |i: usize, request: AcquireRequest<A>| {
+    let (sender1, receiver1) = once_channel();
+
+    let (sender2, receiver2) = once_channel();
+
+    let relay = Relay {
+      grant: receiver1,
+      granted: request.granted,
+      claim: request.claim,
+      claimed: sender2,
+    };
+
+    providers[i].send(AcquireRequest {
+      mode: request.mode,
+      options: request.options,
+      granted: sender1,
+      claim: receiver2,
+      linear: request.linear,
+      failed: request.failed,
+    })?;
+
+    let (probe, probed) = probe_request();
+
+    providers[i].send(probe)?;
+
+    probe_senders[i].send((Some(relay), probed))
+  };
+
+  let mut replicas = vec![Replica::Busy; providers.len()];
+
+  let mut running = 0;
+
+  for (i, replica) in replicas.iter_mut().enumerate() {
+    let (probe, probed) = probe_request();
+
+    if providers[i]
+      .send(probe)
+      .and_then(|()| probe_senders[i].send((None, probed)))
+      .is_ok()
+    {
+      running += 1;
+    } else {
+      *replica = Replica::Stopped;
     }
-  });
+  }
+
+  let mut waiting: VecDeque<AcquireRequest<A>> = VecDeque::new();
+
+  let mut next = 0;
+
+  let mut served: HashMap<u64, u64> = HashMap::new();
+
+  // When each replica was given the lock it is currently holding.
+  let mut held: Vec<Option<Instant>> = vec![None; replicas.len()];
+
+  let mut active = 0;
+
+  let mut depth = 0;
+
+  let mut tick = 0;
+
+  let mut closed = false;
+
+  let mut stopping = false;
+
+  let mut exhausted = false;
+
+  let mut listening = true;
+
+  let mut shutdown = pin!(shutdown.recv());
+
+  loop {
+    if running == 0 {
+      info!("[run_shared_session] shared session has terminated");
+
+      return;
+    }
+
+    if waiting.len() != depth {
+      depth = waiting.len();
+
+      report(&config.metrics, |metrics| metrics.queue_depth(depth));
+    }
+
+    let idle = (0..replicas.len())
+      .map(|k| (next + k) % replicas.len())
+      .find(|&i| replicas[i] == Replica::Idle);
+
+    if let Some(i) = idle {
+      if let Some(request) = pick_next(&mut waiting, config.scheduling, &served)
+      {
+        if request.granted.is_closed() {
+          debug!("[run_shared_session] dropping abandoned acquire request");
+
+          continue;
+        }
+
+        let client = request.options.client;
+
+        if send_request(i, request).is_err() {
+          info!("[run_shared_session] shared session replica has terminated");
+
+          replicas[i] = Replica::Stopped;
+
+          running -= 1;
+        } else {
+          replicas[i] = Replica::Busy;
+
+          next = i + 1;
+
+          tick += 1;
+
+          if let Some(client) = client {
+            served.insert(client, tick);
+          }
+        }
+
+        continue;
+      }
+    }
+
+    if closed && waiting.is_empty() && !replicas.contains(&Replica::Busy) {
+      info!("[run_shared_session] terminating shared session");
+
+      // Acquires made after a shutdown are refused for as long as
+      // there are channels left to make them.
+      if stopping && !exhausted {
+        let metrics = config.metrics.clone();
+
+        runtime::spawn(async move {
+          while let Some(request) = requests.recv().await {
+            refuse(request, &metrics);
+          }
+        });
+      }
+
+      return;
+    }
+
+    let any_idle = idle.is_some();
+
+    // With the wait policy, waiting acquires beyond the capacity are
+    // left unread until there is room for them, which holds up
+    // acquirers in sending them. Try-acquires are always taken. A
+    // request that finds a provider idle never counts against the
+    // capacity.
+    let has_room = waiting.len() < config.capacity || any_idle;
+
+    let accepting = if stopping { !exhausted } else { !closed };
+
+    let holding =
+      !stopping && config.overflow == OverflowPolicy::Wait && !has_room;
+
+    // Branches are polled in order rather than at random, so that the
+    // dispatcher makes the same choices on every run of a simulation.
+    let event = {
+      let mut request = pin!(async {
+        if !accepting {
+          pending().await
+        } else if holding {
+          requests.recv_try().await
+        } else {
+          requests.recv().await
+        }
+      }
+      .fuse());
+
+      let mut stop = pin!(async {
+        if listening {
+          shutdown.as_mut().await.is_ok()
+        } else {
+          pending().await
+        }
+      }
+      .fuse());
+
+      let mut replica = pin!(idle_receiver.recv().fuse());
+
+      select_biased! {
+        m_request = request => DispatchEvent::Request(m_request),
+        m_shutdown = stop => DispatchEvent::Shutdown(m_shutdown),
+        m_event = replica => DispatchEvent::Replica(m_event),
+      }
+    };
+
+    match event {
+      DispatchEvent::Request(m_request) => {
+        match m_request {
+          Some(request) if stopping => {
+            debug!("[run_shared_session] refusing acquire after shutdown");
+
+            refuse(request, &config.metrics);
+          }
+          Some(request) => {
+            debug!("[run_shared_session] received acquire request");
+
+            if request.mode == AcquireMode::Try {
+              if !any_idle || !waiting.is_empty() {
+                debug!("[run_shared_session] shared session is busy");
+
+                refuse(request, &config.metrics);
+              } else {
+                waiting.push_back(request);
+              }
+            } else if has_room {
+              waiting.push_back(request);
+            } else {
+              match config.overflow {
+                OverflowPolicy::Wait | OverflowPolicy::Reject => {
+                  debug!("[run_shared_session] acquire queue is full");
+
+                  refuse(request, &config.metrics);
+                }
+                // With a capacity of zero there is no older request
+                // to make room for the new one.
+                OverflowPolicy::ShedOldest => match waiting.pop_front() {
+                  Some(oldest) => {
+                    debug!("[run_shared_session] shedding oldest acquire");
+
+                    refuse(oldest, &config.metrics);
+
+                    waiting.push_back(request);
+                  }
+                  None => {
+                    debug!("[run_shared_session] acquire queue is full");
+
+                    refuse(request, &config.metrics);
+                  }
+                },
+              }
+            }
+          }
+          None => {
+            closed = true;
+
+            exhausted = true;
+          }
+        }
+      }
+      DispatchEvent::Shutdown(stopped) => {
+        listening = false;
+
+        // A dropped handle leaves the shared session running as usual.
+        if stopped {
+          info!("[run_shared_session] shutting down shared session");
+
+          closed = true;
+
+          stopping = true;
+
+          for request in waiting.drain(..) {
+            refuse(request, &config.metrics);
+          }
+        }
+      }
+      DispatchEvent::Replica(m_event) => {
+        if let Some(ReplicaEvent::Idle(i, _)) = m_event {
+          if let Some(since) = held[i].take() {
+            active -= 1;
+
+            report(&config.metrics, |metrics| {
+              metrics.lock_released(since.elapsed(), active)
+            });
+          }
+        }
+
+        match m_event {
+          Some(ReplicaEvent::Acquired(i)) => {
+            held[i] = Some(Instant::now());
+
+            active += 1;
+
+            report(&config.metrics, |metrics| metrics.lock_acquired(active));
+          }
+          Some(ReplicaEvent::Idle(i, true)) => {
+            replicas[i] = Replica::Idle;
+          }
+          Some(ReplicaEvent::Idle(i, false)) => {
+            info!("[run_shared_session] shared session replica has terminated");
+
+            replicas[i] = Replica::Stopped;
+
+            running -= 1;
+          }
+          None => {
+            info!("[run_shared_session] shared session has terminated");
+
+            return;
+          }
+        }
+      }
+    }
+  }
+}
+
+fn probe_request<A>() -> (AcquireRequest<A>, ReceiverOnce<bool>)
+{
+  let (granted, probed) = once_channel();
+
+  let (_, claim) = once_channel();
+
+  let (linear, _) = once_channel();
+
+  let (failed, _) = once_channel();
+
+  let probe = AcquireRequest {
+    mode: AcquireMode::Wait,
+    options: AcquireOptions::default(),
+    granted,
+    claim,
+    linear,
+    failed,
+  };
+
+  (probe, probed)
+}
+
+fn refuse<A>(
+  request: AcquireRequest<A>,
+  metrics: &Option<Arc<dyn SharedMetrics>>,
+)
+{
+  let _ = request.granted.send(false);
+
+  report(metrics, |metrics| metrics.acquire_refused());
+}
+
+fn report(
+  metrics: &Option<Arc<dyn SharedMetrics>>,
+  record: impl FnOnce(&dyn SharedMetrics),
+)
+{
+  if let Some(metrics) = metrics {
+    record(metrics.as_ref());
+  }
+}
+
+// Take the waiting request that should be given the lock next. Clients
+// that were never served before, and acquires without a client, count
+// as the least recently served.
+fn pick_next<A>(
+  waiting: &mut VecDeque<AcquireRequest<A>>,
+  scheduling: AcquireScheduling,
+  served: &HashMap<u64, u64>,
+) -> Option<AcquireRequest<A>>
+{
+  let last_served = |request: &AcquireRequest<A>| {
+    request
+      .options
+      .client
+      .and_then(|client| served.get(&client).copied())
+      .unwrap_or(0)
+  };
+
+  let index = match scheduling {
+    AcquireScheduling::Fifo => 0,
+    AcquireScheduling::Priority => {
+      let priority = waiting.iter().map(|r| r.options.priority).max()?;
+
+      waiting
+        .iter()
+        .position(|r| r.options.priority == priority)?
+    }
+    AcquireScheduling::Fair => {
+      let priority = waiting.iter().map(|r| r.options.priority).max()?;
+
+      waiting
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.options.priority == priority)
+        .min_by_key(|(_, r)| last_served(r))?
+        .0
+    }
+  };
+
+  waiting.remove(index)
+}
+
+impl Relay
+{
+  // Pass on the grant from the provider, and the claim back from the
+  // acquirer. Returns whether the acquirer has got the lock.
+  async fn run(self) -> bool
+  {
+    let grant = match self.grant.recv().await {
+      Ok(grant) => grant,
+      Err(_) => return false,
+    };
+
+    if self.granted.send(grant).is_err() || !grant {
+      return false;
+    }
+
+    self.claim.recv().await.is_ok() && self.claimed.send(()).is_ok()
+  }
+}
+
+impl SharedShutdown
+{
+  // Stop accepting acquires, refuse those still waiting, and resolve
+  // once the linear session currently holding the lock is released
+  // and the shared session has stopped.
+  pub async fn shutdown(mut self) -> Result<(), SessionError>
+  {
+    // The dispatcher is already gone if every channel was dropped.
+    let _ = self.signal.send(());
+
+    self.dispatcher.await?;
+
+    join_providers(&mut self.providers).await
+  }
+
+  // Like shutdown, but stop waiting for the linear session holding the
+  // lock after the given duration, and abort the shared session.
+  pub async fn shutdown_timeout(
+    self,
+    duration: Duration,
+  ) -> Result<(), SessionError>
+  {
+    let _ = self.signal.send(());
+
+    let mut dispatcher = self.dispatcher;
+
+    let mut providers = self.providers;
+
+    let res = runtime::timeout(duration, async {
+      (&mut dispatcher).await?;
+
+      join_providers(&mut providers).await
+    })
+    .await;
+
+    match res {
+      Ok(res) => res,
+      Err(_) => {
+        info!("[run_shared_session] aborting shared session after timeout");
+
+        dispatcher.abort();
+
+        for provider in providers {
+          provider.abort();
+        }
+
+        Err(SessionError::TimedOut)
+      }
+    }
+  }
+
+  // Resolve once the shared session has stopped on its own, after
+  // every channel to it is dropped. Failures are already logged by the
+  // replicas themselves.
+  async fn stopped(mut self)
+  {
+    let _ = (&mut self.dispatcher).await;
+
+    let _ = join_providers(&mut self.providers).await;
+  }
+}
+
+// Wait for every replica to stop, and report the first failure.
+async fn join_providers(
+  providers: &mut [JoinHandle<Result<(), SessionError>>]
+) -> Result<(), SessionError>
+{
+  let mut res = Ok(());
+
+  for provider in providers.iter_mut() {
+    let res2 = match provider.await {
+      Ok(res2) => res2,
+      Err(err) => Err(err.into()),
+    };
+
+    res = res.and(res2);
+  }
 
-  (session2, handle)
+  res
 }
@@ -1,7 +1,12 @@
-use std::marker::PhantomData;
+use std::{
+  marker::PhantomData,
+  time::{
+    Duration,
+    Instant,
+  },
+};
 
 use async_macros::join;
-use tokio::task;
 
 use crate::internal::{
   base::*,
@@ -15,6 +20,10 @@ use crate::internal::{
   },
 };
 
+// Acquirers that give up before claiming the lock, such as through a
+// timeout, are skipped without running the continuation. An acquirer
+// that drops its end before releasing the lock fails the shared
+// session.
 pub fn accept_shared_session<F>(
   cont: impl FnOnce() -> PartialSession<(Lock<F>, ()), F::Applied> + Send + 'static
 ) -> SharedSession<LinearToShared<F>>
@@ -23,54 +32,120 @@ where
   F: SharedRecApp<SharedToLinear<F>>,
   F::Applied: Protocol,
 {
-  unsafe_create_shared_session(
-    move |receiver1: Receiver<(
-      SenderOnce<()>,
-      SenderOnce<LinearToShared<F>>,
-    )>| async move {
-      let cont2 = cont();
+  let mut cont = Some(cont);
 
-      let (sender2, receiver2): (SenderOnce<Lock<F>>, _) = once_channel();
-
-      let (sender4, receiver4): (SenderOnce<F::Applied>, _) = once_channel();
+  unsafe_accept_shared_session(
+    move || cont.take().expect("continuation to only run once")(),
+    false,
+  )
+}
 
-      let m_sender1 = receiver1.recv().await;
+// Like accept_shared_session, but the continuation is run again for
+// the next acquirer if the one holding the lock drops its end before
+// it is released, such as when it is aborted in the middle of the
+// protocol. The shared session then keeps serving from the state it
+// was last released in.
+pub fn accept_shared_session_restartable<F>(
+  cont: impl Fn() -> PartialSession<(Lock<F>, ()), F::Applied> + Send + 'static
+) -> SharedSession<LinearToShared<F>>
+where
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  F::Applied: Protocol,
+{
+  unsafe_accept_shared_session(cont, true)
+}
 
-      match m_sender1 {
-        Some((sender5, sender6)) => {
-          let child1 = task::spawn(async move {
-            debug!("[accept_shared_session] calling cont");
+fn unsafe_accept_shared_session<F>(
+  mut cont: impl FnMut() -> PartialSession<(Lock<F>, ()), F::Applied>
+    + Send
+    + 'static,
+  restartable: bool,
+) -> SharedSession<LinearToShared<F>>
+where
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  F::Applied: Protocol,
+{
+  unsafe_create_shared_session(
+    move |receiver1: Receiver<AcquireRequest<LinearToShared<F>>>| async move {
+      loop {
+        let m_sender1 = receiver1.recv().await;
 
-            unsafe_run_session(cont2, (receiver2, ()), sender4).await;
+        match m_sender1 {
+          Some(AcquireRequest {
+            granted: sender5,
+            claim: receiver5,
+            linear: sender6,
+            failed,
+            ..
+          }) => {
+            drop(failed);
 
-            debug!("[accept_shared_session] returned from cont");
-          });
+            // An acquirer that gave up waiting has dropped its claim.
+            // So has a probe from the dispatcher of run_shared_session,
+            // which takes our grant to mean that we are idle again.
+            if sender5.send(true).is_err() || receiver5.recv().await.is_err() {
+              debug!("[accept_shared_session] skipping unclaimed acquire");
 
-          let child2 = task::spawn(async move {
-            let linear = receiver4.recv().await.unwrap();
+              continue;
+            }
 
-            debug!("[accept_shared_session] received from receiver4");
+            let (sender2, receiver2): (SenderOnce<Lock<F>>, _) = once_channel();
 
-            sender6.send(LinearToShared { linear }).unwrap();
-          });
+            let (sender4, receiver4): (SenderOnce<F::Applied>, _) =
+              once_channel();
 
-          let child3 = task::spawn(async move {
-            sender5.send(()).unwrap();
-          });
+            let cont2 = cont();
 
-          let child4 = task::spawn(async move {
             debug!("[accept_shared_session] sending sender12");
 
-            sender2.send(Lock { unlock: receiver1 }).unwrap();
+            sender2.send(Lock {
+              unlock: receiver1.clone(),
+            })?;
 
             debug!("[accept_shared_session] sent sender12");
-          });
 
-          let _ = join!(child1, child2, child3, child4).await;
-        }
-        None => {
-          // shared session is terminated with all references to it
-          // being dropped
+            let child1 = async move {
+              debug!("[accept_shared_session] calling cont");
+
+              unsafe_run_session(cont2, (receiver2, ()), sender4).await?;
+
+              debug!("[accept_shared_session] returned from cont");
+
+              Ok::<_, SessionError>(())
+            };
+
+            let child2 = trace_action("accept", None, async move {
+              let linear = receiver4.recv().await?;
+
+              debug!("[accept_shared_session] received from receiver4");
+
+              sender6.send(LinearToShared { linear })?;
+
+              Ok::<_, SessionError>(())
+            });
+
+            let (res1, res2) = join!(child1, child2).await;
+
+            // Once the lock is released, the detached session takes
+            // over the acquire requests and handles its own acquirers
+            // being dropped, so a dropped peer can only be ours.
+            match res1.and(res2) {
+              Err(SessionError::PeerDropped) if restartable => {
+                info!(
+                  "[accept_shared_session] acquirer dropped before release, \
+                   accepting again"
+                );
+              }
+              res => return res,
+            }
+          }
+          None => {
+            // shared session is terminated with all references to it
+            // being dropped
+            return Ok(());
+          }
         }
       }
     },
@@ -90,34 +165,49 @@ where
     move |(receiver1, _): (ReceiverOnce<Lock<F>>, C::Endpoints), sender1| async move {
       let (sender3, receiver3) = once_channel::<()>();
 
-      let child1 = task::spawn(async move {
+      let child1 = spawn_child(async move {
         debug!("[detach_shared_session] receiving sender2");
 
-        let Lock { unlock: receiver2 } = receiver1.recv().await.unwrap();
+        let Lock { unlock: receiver2 } = receiver1.recv().await?;
 
-        receiver3.recv().await.unwrap();
+        // An acquirer that is aborted while holding the lock drops
+        // its SharedToLinear endpoint without sending the unlock
+        // signal. Treat this the same as a release, so that the shared
+        // session keeps serving the other acquirers.
+        if receiver3.recv().await.is_err() {
+          debug!("[detach_shared_session] lock dropped without release");
+        }
 
         debug!("[detach_shared_session] received sender2");
 
-        unsafe_run_shared_session(cont, receiver2).await;
+        trace_shared(unsafe_run_shared_session(cont, receiver2)).await?;
 
         debug!("[detach_shared_session] ran cont");
+
+        Ok::<_, SessionError>(())
       });
 
-      let child2 = task::spawn(async move {
+      let child2 = spawn_child(async move {
         debug!("[detach_shared_session] sending sender1");
 
-        sender1
-          .send(SharedToLinear {
-            unlock: sender3,
-            phantom: PhantomData,
-          })
-          .unwrap();
+        let sent = sender1.send(SharedToLinear {
+          unlock: sender3,
+          phantom: PhantomData,
+        });
+
+        if sent.is_err() {
+          debug!("[detach_shared_session] acquirer is gone, releasing lock");
+        }
 
         debug!("[detach_shared_session] sent sender1");
+
+        Ok::<_, SessionError>(())
       });
 
-      let _ = join!(child1, child2).await;
+      let (res1, res2) = join!(child1, child2).await;
+
+      res1??;
+      res2?
     },
   )
 }
@@ -127,7 +217,7 @@ pub fn async_acquire_shared_session<F>(
   cont_builder: impl FnOnce(Z) -> PartialSession<(F::Applied, ()), End>
     + Send
     + 'static,
-) -> task::JoinHandle<()>
+) -> runtime::JoinHandle<Result<(), SessionError>>
 where
   F: Protocol,
   F: SharedRecApp<SharedToLinear<F>>,
@@ -135,9 +225,15 @@ where
 {
   debug!("[async_acquire_shared_session] acquiring shared session");
 
-  let (receiver3, receiver4) = unsafe_receive_shared_channel(shared);
+  let metrics = shared.metrics().cloned();
+
+  let started = Instant::now();
+
+  let m_receivers = unsafe_receive_shared_channel(shared, AcquireMode::Wait);
+
+  runtime::spawn(async move {
+    let (receiver3, sender5, receiver4, receiver5) = m_receivers.await?;
 
-  task::spawn(async move {
     let (sender1, receiver1) = once_channel();
 
     let (sender2, receiver2) = once_channel();
@@ -146,27 +242,45 @@ where
 
     let ctx = (receiver2, ());
 
-    let child1 = task::spawn(async move {
-      let LinearToShared { linear } = receiver4.recv().await.unwrap();
+    let child1 = spawn_child(async move {
+      let LinearToShared { linear } = receiver4.recv().await?;
 
-      sender2.send(linear).unwrap();
-    });
+      sender2.send(linear)?;
 
-    let child2 = task::spawn(async move {
-      unsafe_run_session(cont, ctx, sender1).await;
+      Ok::<_, SessionError>(())
     });
 
-    let child3 = task::spawn(async move {
-      receiver1.recv().await.unwrap();
+    let child2 = spawn_child(unsafe_run_session(cont, ctx, sender1));
+
+    let child3 = spawn_child(async move {
+      receiver1.recv().await?;
+
+      Ok::<_, SessionError>(())
     });
 
-    let child4 = task::spawn(async move {
-      receiver3.recv().await.unwrap();
+    let child4 = spawn_child(async move {
+      let granted = receiver3.recv().await?;
+
+      if let Some(metrics) = metrics {
+        metrics.acquire_completed(started.elapsed(), granted);
+      }
+
+      if !granted {
+        return Err(SessionError::AcquireRefused);
+      }
+
+      sender5.send(())?;
 
       debug!("[async_acquire_shared_session] acquired shared session");
+
+      Ok::<_, SessionError>(())
     });
 
-    let _ = join!(child1, child2, child3, child4).await;
+    let (res1, res2, res3, res4) = join!(child1, child2, child3, child4).await;
+
+    // A refused acquire also drops the endpoints of the other children,
+    // so check the grant first for the refusal to be reported as such.
+    forward_failure(res4?.and(res1?).and(res2?).and(res3?), receiver5).await
   })
 }
 
@@ -175,7 +289,7 @@ pub fn async_acquire_shared_session_with_result<T, F>(
   cont_builder: impl FnOnce(Z) -> PartialSession<(F::Applied, ()), SendValue<T, End>>
     + Send
     + 'static,
-) -> task::JoinHandle<T>
+) -> runtime::JoinHandle<Result<T, SessionError>>
 where
   F: Protocol,
   T: Send + 'static,
@@ -184,9 +298,15 @@ where
 {
   debug!("[async_acquire_shared_session_with_result] acquiring shared session");
 
-  let (receiver3, receiver4) = unsafe_receive_shared_channel(shared);
+  let metrics = shared.metrics().cloned();
+
+  let started = Instant::now();
+
+  let m_receivers = unsafe_receive_shared_channel(shared, AcquireMode::Wait);
+
+  runtime::spawn(async move {
+    let (receiver3, sender5, receiver4, receiver5) = m_receivers.await?;
 
-  task::spawn(async move {
     let (sender1, receiver1) = once_channel();
 
     let (sender2, receiver2) = once_channel();
@@ -195,35 +315,48 @@ where
 
     let ctx = (receiver2, ());
 
-    let child1 = task::spawn(async move {
-      let LinearToShared { linear } = receiver4.recv().await.unwrap();
+    let child1 = spawn_child(async move {
+      let LinearToShared { linear } = receiver4.recv().await?;
 
-      sender2.send(linear).unwrap();
-    });
+      sender2.send(linear)?;
 
-    let child2 = task::spawn(async move {
-      unsafe_run_session(cont, ctx, sender1).await;
+      Ok::<_, SessionError>(())
     });
 
-    let child3 = task::spawn(async move {
-      let SendValue((Value(val), receiver3)) = receiver1.recv().await.unwrap();
+    let child2 = spawn_child(unsafe_run_session(cont, ctx, sender1));
 
-      receiver3.recv().await.unwrap();
+    let child3 = spawn_child(async move {
+      let SendValue((Value(val), receiver3)) = receiver1.recv().await?;
 
-      val
+      receiver3.recv().await?;
+
+      Ok::<_, SessionError>(val)
     });
 
-    let child4 = task::spawn(async move {
-      receiver3.recv().await.unwrap();
+    let child4 = spawn_child(async move {
+      let granted = receiver3.recv().await?;
+
+      if let Some(metrics) = metrics {
+        metrics.acquire_completed(started.elapsed(), granted);
+      }
+
+      if !granted {
+        return Err(SessionError::AcquireRefused);
+      }
+
+      sender5.send(())?;
 
       debug!(
         "[async_acquire_shared_session_with_result] acquired shared session"
       );
+
+      Ok::<_, SessionError>(())
     });
 
-    let (_, _, val, _) = join!(child1, child2, child3, child4).await;
+    let (res1, res2, val, res4) = join!(child1, child2, child3, child4).await;
 
-    val.unwrap()
+    // The grant is checked first, as in async_acquire_shared_session.
+    forward_failure(res4?.and(res1?).and(res2?).and(val?), receiver5).await
   })
 }
 
@@ -239,37 +372,200 @@ where
   C: AppendContext<(F::Applied, ())>,
   F::Applied: Protocol,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
-    let cont2 = cont1(<C::Length as Nat>::nat());
+  acquire_shared_session_with(shared, AcquireMode::Wait, None, cont1, None)
+}
 
-    let (sender2, receiver2) = once_channel();
+pub fn acquire_shared_session_or<F, C, A>(
+  shared: SharedChannel<LinearToShared<F>>,
+  cont1: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+  on_refused: PartialSession<C, A>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: Protocol,
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  C: AppendContext<(F::Applied, ())>,
+  F::Applied: Protocol,
+{
+  acquire_shared_session_with(
+    shared,
+    AcquireMode::Wait,
+    None,
+    cont1,
+    Some(on_refused),
+  )
+}
+
+/*
+      cont :: Δ, F ⊢ A    on_busy :: Δ ⊢ A
+   ==========================================
+     try_acquire (shared, cont, on_busy) :: Δ ⊢ A
+
+   Acquire the shared channel only if no one else holds or is
+   waiting for it, and continue as on_busy otherwise.
+*/
+
+pub fn try_acquire_shared_session<F, C, A>(
+  shared: SharedChannel<LinearToShared<F>>,
+  cont1: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+  on_busy: PartialSession<C, A>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: Protocol,
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  C: AppendContext<(F::Applied, ())>,
+  F::Applied: Protocol,
+{
+  acquire_shared_session_with(
+    shared,
+    AcquireMode::Try,
+    None,
+    cont1,
+    Some(on_busy),
+  )
+}
 
-    let (receiver3, receiver4) = unsafe_receive_shared_channel(shared);
+pub fn acquire_shared_session_with_timeout<F, C, A>(
+  shared: SharedChannel<LinearToShared<F>>,
+  duration: Duration,
+  cont1: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+  on_timeout: PartialSession<C, A>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: Protocol,
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  C: AppendContext<(F::Applied, ())>,
+  F::Applied: Protocol,
+{
+  acquire_shared_session_with(
+    shared,
+    AcquireMode::Wait,
+    Some(duration),
+    cont1,
+    Some(on_timeout),
+  )
+}
 
-    debug!("[acquire_shared_session] acquiring shared endpoint");
+fn acquire_shared_session_with<F, C, A>(
+  shared: SharedChannel<LinearToShared<F>>,
+  mode: AcquireMode,
+  duration: Option<Duration>,
+  cont1: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+  on_refused: Option<PartialSession<C, A>>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: Protocol,
+  F: Protocol,
+  F: SharedRecApp<SharedToLinear<F>>,
+  C: AppendContext<(F::Applied, ())>,
+  F::Applied: Protocol,
+{
+  unsafe_create_session(move |ctx1, sender1| {
+    trace_action(
+      "acquire",
+      Some(<C::Length as Nat>::to_usize()),
+      async move {
+        let metrics = shared.metrics().cloned();
+
+        let started = Instant::now();
+
+        let receive = unsafe_receive_shared_channel(shared, mode);
+
+        // Waiting for the shared session to take the request counts
+        // towards the timeout as well.
+        let received = match duration {
+          Some(duration) => {
+            runtime::timeout(duration, receive).await.ok().transpose()?
+          }
+          None => Some(receive.await?),
+        };
+
+        debug!("[acquire_shared_session] acquiring shared endpoint");
+
+        let m_receiver4 = match received {
+          Some((receiver3, sender5, receiver4, receiver5)) => {
+            let granted = match duration {
+              Some(duration) => receiver3
+                .recv_timeout(duration.saturating_sub(started.elapsed()))
+                .await?
+                .unwrap_or(false),
+              None => receiver3.recv().await?,
+            };
+
+            if granted {
+              sender5.send(())?;
+            }
+
+            Some((receiver4, receiver5)).filter(|_| granted)
+          }
+          None => None,
+        };
+
+        if let Some(metrics) = metrics {
+          metrics.acquire_completed(started.elapsed(), m_receiver4.is_some());
+        }
 
-    receiver3.recv().await.unwrap();
+        let (receiver4, receiver5) = match m_receiver4 {
+          Some(receivers) => receivers,
+          None => {
+            debug!("[acquire_shared_session] shared endpoint not acquired");
 
-    debug!("[acquire_shared_session] acquired shared endpoint");
+            return match on_refused {
+              Some(cont) => unsafe_run_session(cont, ctx1, sender1).await,
+              None => Err(SessionError::AcquireRefused),
+            };
+          }
+        };
 
-    let ctx2 = C::append_context(ctx1, (receiver2, ()));
+        debug!("[acquire_shared_session] acquired shared endpoint");
 
-    let child1 = task::spawn(async move {
-      let LinearToShared { linear } = receiver4.recv().await.unwrap();
+        let cont2 = cont1(<C::Length as Nat>::nat());
 
-      sender2.send(linear).unwrap();
-    });
+        let (sender2, receiver2) = once_channel();
 
-    let child2 = task::spawn(async move {
-      unsafe_run_session(cont2, ctx2, sender1).await;
-    });
+        let ctx2 = C::append_context(ctx1, (receiver2, ()));
 
-    let _ = join!(child1, child2).await;
+        let child1 = spawn_child(async move {
+          let LinearToShared { linear } = receiver4.recv().await?;
 
-    // debug!("[acquire_shared_session] ran cont");
+          sender2.send(linear)?;
+
+          Ok::<_, SessionError>(())
+        });
+
+        let child2 = spawn_child(unsafe_run_session(cont2, ctx2, sender1));
+
+        let (res1, res2) = join!(child1, child2).await;
+
+        forward_failure(res1?.and(res2?), receiver5).await
+      },
+    )
   })
 }
 
+// A forwarded session that fails drops the endpoints it was forwarding
+// to, so prefer the reason given by the forwarder over the dropped peer
+// seen here.
+async fn forward_failure<T>(
+  res: Result<T, SessionError>,
+  failed: ReceiverOnce<ForwardError>,
+) -> Result<T, SessionError>
+{
+  match res {
+    Err(SessionError::PeerDropped) => match failed.recv().await {
+      Ok(err) => Err(err.error.into()),
+      Err(_) => Err(SessionError::PeerDropped),
+    },
+    res => res,
+  }
+}
+
 pub fn release_shared_session<F, C, A, N>(
   _: N,
   cont: PartialSession<N::Target, A>,
@@ -280,22 +576,26 @@ where
   F: Protocol,
   N: ContextLens<C, SharedToLinear<F>, Empty>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
-    let (receiver2, ctx2) = N::extract_source(ctx1);
+  unsafe_create_session(move |ctx1, sender1| {
+    trace_action("release", Some(N::slot_index()), async move {
+      let (receiver2, ctx2) = N::extract_source(ctx1);
+
+      let ctx3 = N::insert_target((), ctx2);
 
-    let ctx3 = N::insert_target((), ctx2);
+      debug!("[release_shared_session] waiting receiver2");
 
-    debug!("[release_shared_session] waiting receiver2");
+      let lock: SharedToLinear<F> = receiver2.recv().await?;
 
-    let lock: SharedToLinear<F> = receiver2.recv().await.unwrap();
+      lock.unlock.send(())?;
 
-    lock.unlock.send(()).unwrap();
+      debug!("[release_shared_session] received receiver2");
 
-    debug!("[release_shared_session] received receiver2");
+      unsafe_run_session(cont, ctx3, sender1).await?;
 
-    unsafe_run_session(cont, ctx3, sender1).await;
+      debug!("[release_shared_session] ran cont");
 
-    debug!("[release_shared_session] ran cont");
+      Ok(())
+    })
   })
 }
 
@@ -316,4 +616,49 @@ where
   {
     acquire_shared_session(self.clone(), cont)
   }
+
+  pub fn acquire_or<C, A>(
+    &self,
+    cont: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+    on_refused: PartialSession<C, A>,
+  ) -> PartialSession<C, A>
+  where
+    C: Context,
+    A: Protocol,
+    C: AppendContext<(F::Applied, ())>,
+  {
+    acquire_shared_session_or(self.clone(), cont, on_refused)
+  }
+
+  pub fn try_acquire<C, A>(
+    &self,
+    cont: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+    on_busy: PartialSession<C, A>,
+  ) -> PartialSession<C, A>
+  where
+    C: Context,
+    A: Protocol,
+    C: AppendContext<(F::Applied, ())>,
+  {
+    try_acquire_shared_session(self.clone(), cont, on_busy)
+  }
+
+  pub fn acquire_with_timeout<C, A>(
+    &self,
+    duration: Duration,
+    cont: impl FnOnce(C::Length) -> PartialSession<C::Appended, A> + Send + 'static,
+    on_timeout: PartialSession<C, A>,
+  ) -> PartialSession<C, A>
+  where
+    C: Context,
+    A: Protocol,
+    C: AppendContext<(F::Applied, ())>,
+  {
+    acquire_shared_session_with_timeout(
+      self.clone(),
+      duration,
+      cont,
+      on_timeout,
+    )
+  }
 }
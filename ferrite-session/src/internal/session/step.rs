@@ -9,9 +9,9 @@ where
   C: Context,
   A: Protocol,
 {
-  unsafe_create_session(move |ins, sender| async move {
+  unsafe_create_step_session(move |ins, sender| async move {
     let cont2 = cont1.await;
 
-    unsafe_run_session(cont2, ins, sender).await;
+    Ok(unsafe_continue_session(cont2, ins, sender))
   })
 }
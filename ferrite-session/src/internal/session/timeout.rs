@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use crate::internal::base::{
+  once_channel,
+  runtime,
+  spawn_child,
+  unsafe_create_session,
+  unsafe_run_session,
+  Context,
+  PartialSession,
+  PositiveProtocol,
+  Session,
+  SessionError,
+};
+
+/*
+      cont :: Δ ⊢ A    on_timeout :: · ⊢ A
+   ==========================================
+     timeout (d, cont, on_timeout) :: Δ ⊢ A
+
+   Run cont, but if it has not started offering A within the
+   given duration, abort it and continue as on_timeout instead.
+   The channels in Δ are dropped together with the aborted cont.
+
+   A has to be positive, so that offering it means that cont has taken
+   the first step of A. A protocol that starts by receiving from the
+   client would be offered right away, leaving that receive unbounded.
+*/
+
+pub fn timeout<C, A>(
+  duration: Duration,
+  cont: PartialSession<C, A>,
+  on_timeout: Session<A>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: PositiveProtocol,
+{
+  unsafe_create_session(move |ctx, sender1| async move {
+    let (sender2, receiver2) = once_channel::<A>();
+
+    let child = spawn_child(unsafe_run_session(cont, ctx, sender2));
+
+    match runtime::timeout(duration, receiver2.recv()).await {
+      Ok(Ok(offer)) => {
+        sender1.send(offer)?;
+
+        child.await?
+      }
+      Ok(Err(err)) => {
+        // cont finished without offering A, so its own error is
+        // more useful than the closed channel.
+        child.await??;
+
+        Err(err.into())
+      }
+      Err(_) => {
+        debug!("[timeout] session step timed out after {:?}", duration);
+
+        child.abort();
+
+        unsafe_run_session(on_timeout, (), sender1).await
+      }
+    }
+  })
+}
+
+pub fn timeout_or_fail<C, A>(
+  duration: Duration,
+  cont: PartialSession<C, A>,
+) -> PartialSession<C, A>
+where
+  C: Context,
+  A: PositiveProtocol,
+{
+  timeout(
+    duration,
+    cont,
+    unsafe_create_session(|(), _| async { Err(SessionError::TimedOut) }),
+  )
+}
@@ -1,8 +1,9 @@
 use crate::internal::{
   base::{
     once_channel,
-    unsafe_create_session,
-    unsafe_run_session,
+    trace_action,
+    unsafe_continue_session,
+    unsafe_create_step_session,
     Context,
     ContextLens,
     PartialSession,
@@ -21,17 +22,19 @@ where
   A: Protocol,
   C: Context,
 {
-  unsafe_create_session(
-    move |ctx, sender1: SenderOnce<ReceiveValue<T, A>>| async move {
-      let (sender2, receiver2) = once_channel();
+  unsafe_create_step_session(
+    move |ctx, sender1: SenderOnce<ReceiveValue<T, A>>| {
+      trace_action("receive_value", None, async move {
+        let (sender2, receiver2) = once_channel();
 
-      sender1.send(ReceiveValue(sender2)).unwrap();
+        sender1.send(ReceiveValue(sender2))?;
 
-      let (Value(val), sender3) = receiver2.recv().await.unwrap();
+        let (Value(val), sender3) = receiver2.recv().await?;
 
-      let cont2 = cont(val);
+        let cont2 = cont(val);
 
-      unsafe_run_session(cont2, ctx, sender3).await;
+        Ok(unsafe_continue_session(cont2, ctx, sender3))
+      })
     },
   )
 }
@@ -48,17 +51,19 @@ where
   T: Send + 'static,
   N: ContextLens<C, ReceiveValue<T, B>, B>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
-    let (receiver1, ctx2) = N::extract_source(ctx1);
+  unsafe_create_step_session(move |ctx1, sender1| {
+    trace_action("send_value", Some(N::slot_index()), async move {
+      let (receiver1, ctx2) = N::extract_source(ctx1);
 
-    let ReceiveValue(sender2) = receiver1.recv().await.unwrap();
+      let ReceiveValue(sender2) = receiver1.recv().await?;
 
-    let (sender3, receiver3) = once_channel();
+      let (sender3, receiver3) = once_channel();
 
-    let ctx3 = N::insert_target(receiver3, ctx2);
+      let ctx3 = N::insert_target(receiver3, ctx2);
 
-    sender2.send((Value(val), sender3)).unwrap();
+      sender2.send((Value(val), sender3))?;
 
-    unsafe_run_session(cont, ctx3, sender1).await;
+      Ok(unsafe_continue_session(cont, ctx3, sender1))
+    })
   })
 }
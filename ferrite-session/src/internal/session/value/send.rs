@@ -1,11 +1,9 @@
-use async_macros::join;
-use tokio::task;
-
 use crate::internal::{
   base::{
     once_channel,
-    unsafe_create_session,
-    unsafe_run_session,
+    trace_action,
+    unsafe_continue_session,
+    unsafe_create_step_session,
     Context,
     ContextLens,
     PartialSession,
@@ -24,18 +22,14 @@ where
   A: Protocol,
   C: Context,
 {
-  unsafe_create_session(move |ctx, sender1| async move {
-    let (sender2, receiver2) = once_channel();
-
-    let child1 = task::spawn(async move {
-      sender1.send(SendValue((Value(val), receiver2))).unwrap();
-    });
+  unsafe_create_step_session(move |ctx, sender1| {
+    trace_action("send_value", None, async move {
+      let (sender2, receiver2) = once_channel();
 
-    let child2 = task::spawn(async move {
-      unsafe_run_session(cont, ctx, sender2).await;
-    });
+      sender1.send(SendValue((Value(val), receiver2)))?;
 
-    let _ = join!(child1, child2).await;
+      Ok(unsafe_continue_session(cont, ctx, sender2))
+    })
   })
 }
 
@@ -56,15 +50,17 @@ where
   T: Send + 'static,
   N: ContextLens<C, SendValue<T, A>, A>,
 {
-  unsafe_create_session(move |ctx1, sender| async move {
-    let (receiver1, ctx2) = N::extract_source(ctx1);
+  unsafe_create_step_session(move |ctx1, sender| {
+    trace_action("receive_value", Some(N::slot_index()), async move {
+      let (receiver1, ctx2) = N::extract_source(ctx1);
 
-    let SendValue((Value(val), receiver2)) = receiver1.recv().await.unwrap();
+      let SendValue((Value(val), receiver2)) = receiver1.recv().await?;
 
-    let ctx3 = N::insert_target(receiver2, ctx2);
+      let ctx3 = N::insert_target(receiver2, ctx2);
 
-    let cont2 = cont(val);
+      let cont2 = cont(val);
 
-    unsafe_run_session(cont2, ctx3, sender).await;
+      Ok(unsafe_continue_session(cont2, ctx3, sender))
+    })
   })
 }
@@ -1,6 +1,3 @@
-use async_macros::join;
-use tokio::task;
-
 use crate::internal::{
   base::*,
   protocol::*,
@@ -15,22 +12,20 @@ where
   T: Send + 'static,
   T::Unwrap: Protocol,
 {
-  unsafe_create_session(move |ctx, sender1| async move {
+  unsafe_create_step_session(move |ctx, sender1| async move {
     let (sender2, receiver) = once_channel();
 
-    let child1 = task::spawn(async move {
-      let val = receiver.recv().await.unwrap();
+    let step = unsafe_continue_session(cont, ctx, sender2);
 
-      sender1
-        .send(Wrap {
-          unwrap: Box::new(val),
-        })
-        .unwrap();
-    });
+    Ok(step.with_forward(async move {
+      let val = receiver.recv().await?;
 
-    let child2 = task::spawn(unsafe_run_session(cont, ctx, sender2));
+      sender1.send(Wrap {
+        unwrap: Box::new(val),
+      })?;
 
-    let _ = join!(child1, child2).await;
+      Ok(())
+    }))
   })
 }
 
@@ -44,21 +39,21 @@ where
   T: Wrapper + Send + 'static,
   N: ContextLens<C, Wrap<T>, T::Unwrap>,
 {
-  unsafe_create_session(move |ctx1, sender1| async move {
+  unsafe_create_step_session(move |ctx1, sender1| async move {
     let (receiver1, ctx2) = N::extract_source(ctx1);
 
     let (sender2, receiver2) = once_channel();
 
     let ctx3 = N::insert_target(receiver2, ctx2);
 
-    let child1 = task::spawn(async move {
-      let wrapped = receiver1.recv().await.unwrap();
+    let step = unsafe_continue_session(cont, ctx3, sender1);
 
-      sender2.send(*wrapped.unwrap).unwrap();
-    });
+    Ok(step.with_forward(async move {
+      let wrapped = receiver1.recv().await?;
 
-    let child2 = task::spawn(unsafe_run_session(cont, ctx3, sender1));
+      sender2.send(*wrapped.unwrap)?;
 
-    let _ = join!(child1, child2).await;
+      Ok(())
+    }))
   })
 }
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::sleep;
+
+type SharedCounter = LinearToShared<SendValue<u64, Release>>;
+
+fn counter(count: u64) -> SharedSession<SharedCounter>
+{
+  accept_shared_session(move || {
+    send_value(count, detach_shared_session(counter(count + 1)))
+  })
+}
+
+// Holds on to the lock, so that every other acquire overflows.
+fn hold(counter: SharedChannel<SharedCounter>) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |_| {
+      step(async move {
+        sleep(Duration::from_millis(500)).await;
+
+        release_shared_session(chan, terminate())
+      })
+    })
+  })
+}
+
+fn read(counter: SharedChannel<SharedCounter>) -> Session<End>
+{
+  acquire_shared_session(counter, move |chan| {
+    receive_value_from(chan, move |_| {
+      release_shared_session(chan, terminate())
+    })
+  })
+}
+
+#[tokio::test]
+async fn rejected_acquire_is_refused()
+{
+  let (counter, _shutdown) = run_shared_session_with_config(
+    counter(0),
+    SharedSessionConfig {
+      capacity: 1,
+      overflow: OverflowPolicy::Reject,
+      ..SharedSessionConfig::default()
+    },
+  );
+
+  let holder = tokio::spawn(run_session(hold(counter.clone())));
+
+  sleep(Duration::from_millis(100)).await;
+
+  // Takes the only place in the queue while the lock is held.
+  let waiter = tokio::spawn(run_session(read(counter.clone())));
+
+  sleep(Duration::from_millis(100)).await;
+
+  let res = async_acquire_shared_session(counter.clone(), |chan| {
+    receive_value_from(chan, move |_| {
+      release_shared_session(chan, terminate())
+    })
+  })
+  .await
+  .unwrap();
+
+  assert!(matches!(res, Err(SessionError::AcquireRefused)));
+
+  let res = async_acquire_shared_session_with_result(counter, |chan| {
+    receive_value_from(chan, move |count| {
+      release_shared_session(chan, send_value(count, terminate()))
+    })
+  })
+  .await
+  .unwrap();
+
+  assert!(matches!(res, Err(SessionError::AcquireRefused)));
+
+  holder.await.unwrap().unwrap();
+
+  waiter.await.unwrap().unwrap();
+}
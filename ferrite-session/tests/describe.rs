@@ -0,0 +1,73 @@
+use ferrite_session::{
+  either::*,
+  prelude::*,
+};
+
+mod a
+{
+  pub struct Foo;
+}
+
+mod b
+{
+  pub struct Foo;
+}
+
+impl DescribeValue for a::Foo
+{
+  fn value_name() -> String
+  {
+    "a::Foo".to_string()
+  }
+}
+
+impl DescribeValue for b::Foo
+{
+  fn value_name() -> String
+  {
+    "b::Foo".to_string()
+  }
+}
+
+type Inner<A> = InternalChoice<Either<SendValue<A, Z>, S<Z>>>;
+
+type Stream = Rec<ExternalChoice<Either<Rec<Inner<String>>, End>>>;
+
+#[test]
+fn payload_types_are_told_apart_by_name()
+{
+  assert_ne!(
+    describe::<SendValue<a::Foo, End>>(),
+    describe::<SendValue<b::Foo, End>>()
+  );
+
+  assert_eq!(
+    describe::<ReceiveValue<(String, Vec<u8>), End>>().to_string(),
+    "(String, Vec<u8>) ⊃ 1"
+  );
+}
+
+#[test]
+fn unfolded_recursion_is_described_with_its_context()
+{
+  // The inner recursion, once the outer one has been unfolded, refers
+  // to the outer one through its context rather than a free variable.
+  let unfolded = describe::<RecX<(Stream, ()), Inner<String>>>();
+
+  let ProtocolDesc::Rec(inner) = unfolded
+  else {
+    panic!("expected a recursive protocol");
+  };
+
+  let ProtocolDesc::InternalChoice(branches) = *inner
+  else {
+    panic!("expected an internal choice");
+  };
+
+  assert_eq!(branches[1], describe::<Stream>());
+
+  assert_ne!(
+    describe::<RecX<(Stream, ()), Inner<String>>>(),
+    describe::<RecX<(Rec<End>, ()), Inner<String>>>()
+  );
+}
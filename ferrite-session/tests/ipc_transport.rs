@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn dropped_recv_leaves_nothing_behind()
+{
+  let (sender1, receiver1) = IpcTransport::channel().unwrap();
+
+  sender1.send("value", 1u64).await.unwrap();
+
+  assert_eq!(receiver1.recv::<u64>("value").await.unwrap(), 1);
+
+  // A receive that is given up on does not take the next frame.
+  let pending = receiver1.recv::<u64>("value");
+
+  assert!(timeout(Duration::from_millis(50), pending).await.is_err());
+
+  sender1.send("value", 2u64).await.unwrap();
+
+  assert_eq!(receiver1.recv::<u64>("value").await.unwrap(), 2);
+
+  // Abandon the session with a receive pending and a frame in flight.
+  let pending = receiver1.recv::<u64>("value");
+
+  assert!(timeout(Duration::from_millis(50), pending).await.is_err());
+
+  let (sender2, receiver2) = sender1.pair().unwrap();
+
+  sender1.send("value", 3u64).await.unwrap();
+
+  drop((sender1, receiver1));
+
+  // The next session only sees its own frames.
+  sender2.send("value", 4u64).await.unwrap();
+
+  sender2.send("value", 5u64).await.unwrap();
+
+  assert_eq!(receiver2.recv::<u64>("value").await.unwrap(), 4);
+
+  assert_eq!(receiver2.recv::<u64>("value").await.unwrap(), 5);
+}
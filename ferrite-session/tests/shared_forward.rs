@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use ferrite_session::prelude::*;
+use tokio::{
+  net::{
+    TcpListener,
+    TcpStream,
+  },
+  time::{
+    sleep,
+    timeout,
+  },
+};
+
+type SharedAdder = LinearToShared<ReceiveValue<u64, SendValue<u64, Release>>>;
+
+fn adder(total: u64) -> SharedSession<SharedAdder>
+{
+  accept_shared_session_restartable(move || {
+    receive_value(move |n| {
+      send_value(total + n, detach_shared_session(adder(total + n)))
+    })
+  })
+}
+
+fn add(
+  adder: SharedChannel<SharedAdder>,
+  n: u64,
+) -> Session<SendValue<u64, End>>
+{
+  acquire_shared_session(adder, move |chan| {
+    send_value_to(
+      chan,
+      n,
+      receive_value_from(chan, move |total| {
+        release_shared_session(chan, send_value(total, terminate()))
+      }),
+    )
+  })
+}
+
+// Acquires the adder and never gets to send it a value.
+fn stall(adder: SharedChannel<SharedAdder>) -> Session<End>
+{
+  acquire_shared_session(adder, move |chan| {
+    step(async move {
+      sleep(Duration::from_secs(60)).await;
+
+      send_value_to(
+        chan,
+        100,
+        receive_value_from(chan, move |_| {
+          release_shared_session(chan, terminate())
+        }),
+      )
+    })
+  })
+}
+
+async fn add_within(
+  adder: SharedChannel<SharedAdder>,
+  n: u64,
+) -> u64
+{
+  timeout(
+    Duration::from_secs(5),
+    run_session_with_result(add(adder, n)),
+  )
+  .await
+  .expect("acquire timed out")
+  .expect("session failed")
+}
+
+async fn abort_remote_acquirer(
+  local: SharedChannel<SharedAdder>,
+  remote: SharedChannel<SharedAdder>,
+)
+{
+  assert_eq!(add_within(remote.clone(), 1).await, 1);
+
+  let stalled = tokio::spawn(run_session(stall(remote.clone())));
+
+  sleep(Duration::from_millis(100)).await;
+
+  stalled.abort();
+
+  // The value of the stalled acquirer never reached the adder, so both
+  // go on from the total it was last released with.
+  assert_eq!(add_within(remote, 2).await, 3);
+
+  assert_eq!(add_within(local, 3).await, 6);
+}
+
+#[tokio::test]
+async fn ipc_acquirer_aborted_mid_protocol()
+{
+  let local = run_shared_session(adder(0));
+
+  let serialized =
+    serialize_shared_channel(local.clone(), &IpcTransport::channel().unwrap().0)
+      .unwrap();
+
+  let remote = deserialize_shared_channel(serialized).unwrap();
+
+  abort_remote_acquirer(local, remote).await;
+}
+
+#[tokio::test]
+async fn socket_acquirer_aborted_mid_protocol()
+{
+  let local = run_shared_session(adder(0));
+
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+  let address = listener.local_addr().unwrap();
+
+  tokio::spawn(listen_shared_channel(local.clone(), listener, Bincode));
+
+  let remote =
+    connect_shared_channel(TcpStream::connect(address).await.unwrap(), Bincode)
+      .await
+      .unwrap();
+
+  abort_remote_acquirer(local, remote).await;
+}
@@ -0,0 +1,64 @@
+use ferrite_session::prelude::*;
+
+type Doubler = ReceiveValue<u64, SendValue<u64, End>>;
+
+fn doubler() -> Session<Doubler>
+{
+  receive_value(|n: u64| send_value(n * 2, terminate()))
+}
+
+fn client() -> Session<ReceiveChannel<Doubler, End>>
+{
+  receive_channel(|doubler| {
+    send_value_to(
+      doubler,
+      21,
+      receive_value_from(doubler, move |doubled| {
+        assert_eq!(doubled, 42);
+
+        wait(doubler, terminate())
+      }),
+    )
+  })
+}
+
+#[tokio::test]
+async fn replay_keeps_order_across_parties()
+{
+  let (doubler, recorder) = record_session::<_, Bincode>(doubler());
+
+  run_session(apply_channel(client(), doubler)).await.unwrap();
+
+  let transcript = recorder.transcript();
+
+  replay_transcript(self::doubler(), &transcript, Bincode)
+    .await
+    .unwrap();
+
+  // The provider now acknowledges the value of the client before it is
+  // sent, which the messages of each party on their own do not show.
+  let mut reordered = transcript.clone();
+
+  let value = reordered
+    .entries
+    .iter()
+    .position(|entry| entry.from == Party::Client && entry.step == "value")
+    .unwrap();
+
+  let ack = reordered
+    .entries
+    .iter()
+    .position(|entry| entry.from == Party::Provider && entry.step == "ack")
+    .unwrap();
+
+  let entry = reordered.entries.remove(value);
+
+  reordered.entries.insert(ack, entry);
+
+  let res = replay_transcript(self::doubler(), &reordered, Bincode).await;
+
+  assert!(matches!(
+    res,
+    Err(SessionError::TransportFailed(ChannelError::ProtocolDesync(_)))
+  ));
+}